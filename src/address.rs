@@ -29,7 +29,7 @@ impl fmt::Display for Zone {
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone, Copy)]
 pub enum DataBuffer {
     TempKey,
     MessageDigest,
@@ -154,3 +154,66 @@ impl Address {
         }
     }
 }
+
+/// Parses the textual form printed by the `Display` impl, one of
+/// `config:<block>,<offset>`, `otp:<block>,<offset>` or
+/// `slot:<slot>,<block>,<offset>` with decimal numbers. `slot:<slot>` is
+/// short for the start of the slot.
+impl FromStr for Address {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (kind, args) = s.split_once(':').ok_or_else(Error::invalid_address)?;
+        let args = args
+            .split(',')
+            .map(|arg| arg.trim().parse::<u8>())
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|_| Error::invalid_address())?;
+        match (kind.trim().to_lowercase().as_str(), &args[..]) {
+            ("config", [block, offset]) => Self::config(*block, *offset),
+            ("otp", [block, offset]) => Self::otp(*block, *offset),
+            ("slot", [slot]) => Self::data(*slot, 0, 0),
+            ("slot", [slot, block, offset]) => Self::data(*slot, *block, *offset),
+            _ => Err(Error::invalid_address()),
+        }
+    }
+}
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Config(addr) => write!(f, "config:{},{}", addr.block(), addr.offset()),
+            Self::Otp(addr) => write!(f, "otp:{},{}", addr.block(), addr.offset()),
+            Self::Data(addr) => {
+                write!(f, "slot:{},{},{}", addr.slot(), addr.block(), addr.offset())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_str() {
+        let address: Address = "config:2,5".parse().expect("address");
+        assert_eq!(Address::config(2, 5).expect("address"), address);
+        assert_eq!("config:2,5", address.to_string());
+        let address: Address = "slot:7".parse().expect("address");
+        assert_eq!("slot:7,0,0", address.to_string());
+        let address: Address = "slot:8,12,3".parse().expect("address");
+        assert_eq!(address, address.to_string().parse().expect("address"));
+
+        for bad in [
+            "config:2",
+            "config:5,0",
+            "slot:16",
+            "slot:3,2,0",
+            "otp:x,1",
+            "key:1",
+        ] {
+            assert!(bad.parse::<Address>().is_err(), "{}", bad);
+        }
+    }
+}
@@ -0,0 +1,163 @@
+//! An async version of [`Ecc`](crate::Ecc) for use in tokio based services.
+//!
+//! Each transport operation runs on the blocking thread pool and the command
+//! execution delays are awaited with the tokio timer, so the runtime worker
+//! threads are never blocked on the chip.
+
+use crate::{
+    command::{EccCommand, EccResponse},
+    constants::{ATCA_CMD_SIZE_MAX, WAKE_DELAY},
+    ecc::CMD_RETRIES,
+    secret::Secret,
+    transport::{Transport, TransportProtocol},
+    Address, DataBuffer, Error, Result,
+};
+use bytes::{BufMut, Bytes, BytesMut};
+use sha2::{Digest, Sha256};
+use std::{
+    io,
+    sync::{Arc, Mutex},
+};
+use tokio::{task, time};
+
+pub struct AsyncEcc {
+    transport: Arc<Mutex<TransportProtocol>>,
+}
+
+impl AsyncEcc {
+    pub async fn from_path(path: &str, address: u16) -> Result<Self> {
+        let path = path.to_string();
+        let transport = task::spawn_blocking(move || TransportProtocol::from_path(&path, address))
+            .await
+            .map_err(join_error)??;
+        Ok(Self {
+            transport: Arc::new(Mutex::new(transport)),
+        })
+    }
+
+    pub async fn random(&mut self) -> Result<Bytes> {
+        self.send_command(&EccCommand::random()).await
+    }
+
+    pub async fn read(&mut self, read_32: bool, address: &Address) -> Result<Bytes> {
+        self.send_command(&EccCommand::read(read_32, address.clone()))
+            .await
+    }
+
+    pub async fn write(&mut self, address: &Address, bytes: &[u8]) -> Result {
+        self.send_command(&EccCommand::write(address.clone(), bytes))
+            .await
+            .map(|_| ())
+    }
+
+    /// Signs the SHA256 digest of the given data with the private key in the
+    /// given slot. See [`Ecc::sign`](crate::Ecc::sign).
+    pub async fn sign(&mut self, key_slot: u8, data: &[u8]) -> Result<Bytes> {
+        let digest = Sha256::digest(data);
+        let _ = self
+            .send_command_retries(&EccCommand::random(), false, 1)
+            .await?;
+        let _ = self
+            .send_command_retries(
+                &EccCommand::nonce(DataBuffer::MessageDigest, Bytes::copy_from_slice(&digest)),
+                false,
+                1,
+            )
+            .await?;
+        self.send_command_retries(
+            &EccCommand::sign(DataBuffer::MessageDigest, key_slot),
+            true,
+            1,
+        )
+        .await
+    }
+
+    async fn send_command(&mut self, command: &EccCommand) -> Result<Bytes> {
+        self.send_command_retries(command, true, CMD_RETRIES).await
+    }
+
+    async fn send_command_retries(
+        &mut self,
+        command: &EccCommand,
+        sleep: bool,
+        retries: u8,
+    ) -> Result<Bytes> {
+        let (flag, delay) = {
+            let transport = self.lock_transport();
+            (
+                transport.put_command_flag(),
+                transport.command_duration(command),
+            )
+        };
+        for retry in 0..retries {
+            let mut buf = Secret(BytesMut::with_capacity(ATCA_CMD_SIZE_MAX as usize));
+            buf.put_u8(flag);
+            command.bytes_into(&mut buf);
+
+            // a chip that doesn't answer the wake isn't worth retrying
+            let sent = self
+                .blocking(move |transport| {
+                    transport.send_wake(WAKE_DELAY)?;
+                    Ok(transport.send_buf(&buf[..]))
+                })
+                .await?;
+            match sent {
+                Err(err) if !err.is_retryable() => return Err(err),
+                Err(_) => continue,
+                Ok(()) => (),
+            }
+
+            time::sleep(delay).await;
+
+            let received = self
+                .blocking(move |transport| {
+                    let mut buf = Secret(BytesMut::with_capacity(ATCA_CMD_SIZE_MAX as usize));
+                    transport.recv_buf(&mut buf)?;
+                    if sleep {
+                        transport.send_sleep();
+                    }
+                    Ok(buf)
+                })
+                .await;
+            let buf = match received {
+                Ok(buf) => buf,
+                Err(err) if !err.is_retryable() => return Err(err),
+                Err(_) => continue,
+            };
+
+            let response = match EccResponse::from_bytes(&buf[..]) {
+                Err(Error::Crc) => continue,
+                response => response?,
+            };
+            match response {
+                EccResponse::Data(bytes) => return Ok(bytes),
+                EccResponse::Error(err) if err.is_recoverable() && retry + 1 < retries => continue,
+                EccResponse::Error(err) => return Err(Error::ecc(command.opcode(), err)),
+            }
+        }
+        Err(Error::timeout())
+    }
+
+    /// Runs the given transport operation on the blocking thread pool.
+    async fn blocking<F, T>(&self, f: F) -> Result<T>
+    where
+        F: FnOnce(&mut TransportProtocol) -> Result<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        let transport = self.transport.clone();
+        task::spawn_blocking(move || {
+            let mut transport = transport.lock().unwrap_or_else(|err| err.into_inner());
+            f(&mut transport)
+        })
+        .await
+        .map_err(join_error)?
+    }
+
+    fn lock_transport(&self) -> std::sync::MutexGuard<'_, TransportProtocol> {
+        self.transport.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+fn join_error(err: task::JoinError) -> Error {
+    Error::Io(io::Error::other(err))
+}
@@ -1,13 +1,17 @@
 use crate::{
     constants::{
-        ATCA_ECDH, ATCA_GENKEY, ATCA_INFO, ATCA_LOCK, ATCA_NONCE, ATCA_RANDOM, ATCA_READ,
-        ATCA_RSP_SIZE_MIN, ATCA_SIGN, ATCA_WRITE, CMD_STATUS_BYTE_COMM, CMD_STATUS_BYTE_ECC,
-        CMD_STATUS_BYTE_EXEC, CMD_STATUS_BYTE_PARSE, CMD_STATUS_BYTE_SELF_TEST,
-        CMD_STATUS_BYTE_SUCCESS, CMD_STATUS_BYTE_WATCHDOG,
+        ATCA_AES, ATCA_CHECKMAC, ATCA_COUNTER, ATCA_DERIVE_KEY, ATCA_ECDH, ATCA_GENDIG,
+        ATCA_GENKEY, ATCA_INFO, ATCA_KDF, ATCA_LOCK, ATCA_MAC, ATCA_NONCE, ATCA_PRIVWRITE,
+        ATCA_RANDOM, ATCA_READ, ATCA_RSP_SIZE_MIN, ATCA_SECUREBOOT, ATCA_SELF_TEST, ATCA_SHA,
+        ATCA_SIGN, ATCA_UPDATE_EXTRA, ATCA_VERIFY, ATCA_WRITE, CMD_STATUS_BYTE_COMM,
+        CMD_STATUS_BYTE_ECC, CMD_STATUS_BYTE_EXEC, CMD_STATUS_BYTE_MISCOMPARE,
+        CMD_STATUS_BYTE_PARSE, CMD_STATUS_BYTE_SELF_TEST, CMD_STATUS_BYTE_SUCCESS,
+        CMD_STATUS_BYTE_WATCHDOG,
     },
-    Address, DataBuffer, Result, Zone,
+    Address, DataBuffer, Error, Result, Zone,
 };
 use bitfield::bitfield;
+use bitflags::bitflags;
 use bytes::{Buf, BufMut, Bytes, BytesMut};
 
 #[derive(Debug, PartialEq)]
@@ -25,17 +29,315 @@ impl From<&KeyType> for u8 {
     }
 }
 
+/// A command for the chip, as passed to
+/// [`Transport::command_duration`](crate::Transport::command_duration). The
+/// types of all variant fields are exported from the crate root.
+///
+/// ```
+/// use ecc608_linux::{EccCommand, ShaMode};
+///
+/// let command = EccCommand::sha(ShaMode::Start, &[]);
+/// assert!(matches!(command, EccCommand::Sha { mode: ShaMode::Start, .. }));
+/// ```
 #[derive(Debug, PartialEq)]
 pub enum EccCommand {
-    Info,
-    GenKey { key_type: KeyType, slot: u8 },
-    Read { is_32: bool, address: Address },
-    Write { address: Address, data: Bytes },
-    Lock { zone: Zone },
+    Info {
+        mode: InfoMode,
+        param: u8,
+    },
+    GenKey {
+        key_type: KeyType,
+        slot: u8,
+    },
+    GenKeyDigest {
+        slot: u8,
+        other_data: [u8; 3],
+    },
+    Read {
+        is_32: bool,
+        address: Address,
+    },
+    Write {
+        address: Address,
+        data: Bytes,
+    },
+    WriteEncrypted {
+        address: Address,
+        data: Bytes,
+    },
+    Lock {
+        zone: Zone,
+        summary: Option<u16>,
+    },
+    LockSlot {
+        slot: u8,
+    },
+    Random {
+        seed_update: bool,
+    },
+    Nonce {
+        mode: NonceMode,
+        target: DataBuffer,
+        data: Bytes,
+    },
+    Sign {
+        source: DataBuffer,
+        key_slot: u8,
+    },
+    Ecdh {
+        x: Bytes,
+        y: Bytes,
+        key_slot: u8,
+        /// Keep the shared secret in TempKey instead of returning it
+        to_tempkey: bool,
+    },
+    VerifyExtern {
+        source: DataBuffer,
+        signature: Bytes,
+        public_key: Bytes,
+    },
+    VerifyStored {
+        source: DataBuffer,
+        signature: Bytes,
+        key_slot: u8,
+    },
+    Counter {
+        counter_id: u8,
+        increment: bool,
+    },
+    Sha {
+        mode: ShaMode,
+        data: Bytes,
+    },
+    GenDig {
+        zone: Zone,
+        key_slot: u8,
+        other_data: Bytes,
+    },
+    DeriveKey {
+        key_slot: u8,
+    },
+    PrivWrite {
+        key_slot: u8,
+        data: Bytes,
+    },
+    Mac {
+        mode: MacMode,
+        key_slot: u8,
+        challenge: Bytes,
+    },
+    CheckMac {
+        key_slot: u8,
+        data: Bytes,
+    },
+    SelfTest {
+        tests: SelfTestFlags,
+    },
+    SecureBoot {
+        mode: SecureBootMode,
+        data: Bytes,
+    },
+    UpdateExtra {
+        target: UpdateExtraTarget,
+        value: u8,
+    },
+    Aes {
+        mode: AesMode,
+        key_slot: u8,
+        data: Bytes,
+    },
+    Kdf {
+        algorithm: KdfAlgorithm,
+        source: KdfSource,
+        target: KdfTarget,
+        message: Bytes,
+    },
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum InfoMode {
+    /// The 4 byte device revision
+    Revision = 0x00,
+    /// Whether the ECC private key in the slot given by the parameter is valid
+    KeyValid = 0x01,
+    /// The volatile device state
+    State = 0x02,
+    /// Read or set the GPIO pin when configured as an output
+    Gpio = 0x03,
+    /// Read or set the persistent latch
+    VolKeyPermit = 0x04,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum SecureBootMode {
+    /// The chip verifies the signature over the digest against the
+    /// public key stored in the SecureBoot public key slot.
+    Full = 0x05,
+}
+
+bitflags! {
+    /// The built-in self tests to run with the SelfTest command.
+    #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+    pub struct SelfTestFlags: u8 {
+        /// Random number generator (DRBG)
+        const RNG = 0x01;
+        /// ECDSA sign and verify. The chip tests these together.
+        const ECDSA = 0x02;
+        const ECDH = 0x08;
+        const AES = 0x10;
+        const SHA = 0x20;
+    }
+}
+
+/// The result of a SelfTest command
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct SelfTestResult {
+    /// The tests that were run
+    pub tested: SelfTestFlags,
+    /// The tests that failed
+    pub failed: SelfTestFlags,
+}
+
+impl SelfTestResult {
+    /// Returns true if every test that was run passed.
+    pub fn passed(&self) -> bool {
+        self.failed.is_empty()
+    }
+
+    /// Returns true if the given tests were run and passed.
+    pub fn is_ok(&self, tests: SelfTestFlags) -> bool {
+        self.tested.contains(tests) && !self.failed.intersects(tests)
+    }
+}
+
+/// Selects where the MAC command takes the challenge from. The MAC is always
+/// computed over the key in the given slot followed by the challenge.
+///
+/// The challenge is omitted from the command data exactly when it is taken
+/// from TempKey, so there is no separate mode for an omitted challenge.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum MacMode {
+    /// The 32 byte challenge is supplied as command data
+    Challenge,
+    /// The challenge is omitted from the command and TempKey is used
+    /// instead. TempKey must have been set up by a random Nonce.
+    TempKey,
+}
+
+impl From<&MacMode> for u8 {
+    fn from(v: &MacMode) -> Self {
+        match v {
+            MacMode::Challenge => 0x00,
+            // second block from TempKey; the source flag bit stays clear
+            // for a TempKey set by a random Nonce
+            MacMode::TempKey => 0x01,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum NonceMode {
+    /// TempKey is set to the digest of a generated random number and the
+    /// supplied input number. The random number is returned.
     Random,
-    Nonce { target: DataBuffer, data: Bytes },
-    Sign { source: DataBuffer, key_slot: u8 },
-    Ecdh { x: Bytes, y: Bytes, key_slot: u8 },
+    /// TempKey (or the target buffer) is set to the supplied data
+    Passthrough,
+}
+
+impl From<&NonceMode> for u8 {
+    fn from(v: &NonceMode) -> Self {
+        match v {
+            NonceMode::Random => 0x00,
+            NonceMode::Passthrough => 0x03,
+        }
+    }
+}
+
+/// Selects which key DeriveKey combines with TempKey to produce the new key
+/// in the target slot. This has to match the DeriveKey write configuration of
+/// the target slot.
+#[derive(Debug, PartialEq)]
+pub enum DeriveKeyMode {
+    /// The current key in the target slot is the source key
+    Roll,
+    /// The parent key in the slot's SlotConfig.WriteKey is the source key
+    Create,
+}
+
+/// Selects what the UpdateExtra command updates
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum UpdateExtraTarget {
+    /// Config zone byte 84. Can only be written while it is zero.
+    UserExtra,
+    /// Config zone byte 85. Can only be written while it is zero.
+    UserExtraAdd,
+    /// Decrements the limited use counter of the key in the slot given as
+    /// the value
+    Decrement,
+}
+
+impl From<&UpdateExtraTarget> for u8 {
+    fn from(v: &UpdateExtraTarget) -> Self {
+        match v {
+            UpdateExtraTarget::UserExtra => 0x00,
+            UpdateExtraTarget::UserExtraAdd => 0x01,
+            UpdateExtraTarget::Decrement => 0x02,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum AesMode {
+    Encrypt = 0x00,
+    Decrypt = 0x01,
+    /// Galois field multiply of the two 16 byte inputs, as used by GHASH
+    Gfm = 0x03,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum KdfAlgorithm {
+    /// RFC 5869 HKDF-Expand with HMAC-SHA256, producing 32 bytes
+    Hkdf = 0x40,
+}
+
+/// The key the KDF command derives from
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum KdfSource {
+    TempKey,
+    Slot(u8),
+}
+
+/// Where the KDF command puts the derived key
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum KdfTarget {
+    TempKey,
+    Slot(u8),
+    /// The derived key is returned in the response
+    Output,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ShaMode {
+    Start,
+    HmacStart {
+        key_slot: u8,
+    },
+    Update,
+    End,
+    /// Completes a digest started with HmacStart, producing the HMAC
+    HmacEnd,
+}
+
+impl From<&ShaMode> for u8 {
+    fn from(v: &ShaMode) -> Self {
+        match v {
+            ShaMode::Start => 0x00,
+            ShaMode::HmacStart { .. } => 0x04,
+            ShaMode::Update => 0x01,
+            ShaMode::End => 0x02,
+            ShaMode::HmacEnd => 0x05,
+        }
+    }
 }
 
 bitfield! {
@@ -43,6 +345,7 @@ bitfield! {
     struct ReadWriteParam(u8);
     impl Debug;
     is_32, set_is_32: 7;
+    is_encrypted, set_is_encrypted: 6;
     address_zone, set_address_zone: 1, 0;
 }
 
@@ -81,6 +384,20 @@ impl From<SignParam> for u8 {
     }
 }
 
+bitfield! {
+    #[derive(PartialEq)]
+    struct VerifyParam(u8);
+    impl Debug;
+    u8, mode, set_mode: 1, 0;
+    u8, source, set_source: 5, 5;
+}
+
+impl From<VerifyParam> for u8 {
+    fn from(v: VerifyParam) -> Self {
+        v.0
+    }
+}
+
 bitfield! {
     #[derive(PartialEq)]
     pub struct LockParam(u8);
@@ -96,35 +413,44 @@ impl From<LockParam> for u8 {
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(thiserror::Error, Debug, PartialEq)]
 pub enum EccError {
     /// Command was properly received but the length, command opcode, or
     /// parameters are illegal regardless of the state (volatile and/or EEPROM
     /// configuration) of the ECC. Changes in the value of the command bits
     /// must be made before it is re-attempted.
+    #[error("illegal command length, opcode or parameters")]
     ParseError,
     /// A computation error occurred during ECC processing that caused the
     /// result to be invalid. Retrying the command may result in a successful
     /// execution.
+    #[error("computation fault")]
     Fault,
     /// There was a self test error and the chip is in failure mode waiting for
     /// the failure to be cleared.
+    #[error("chip is in self test failure mode")]
     SelfTestError,
     /// Command was properly received but could not be executed by the device in
     /// its current state. Changes in the device state or the value of the
     /// command bits must be made before it is re-attempted.
+    #[error("command not allowed in the current chip state")]
     ExecError,
     /// Command was not properly received by AT88SHA204 and should be
     /// re-transmitted by the I/O driver in the system. No attempt was made to
     /// parse or execute the command.
+    #[error("command not received correctly")]
     CommsError,
     /// There is insufficient time to execute the given command before the
     /// watchdog timer will expire. The system must reset the watchdog timer by
     /// entering the idle or sleep modes.
+    #[error("watchdog about to expire")]
     WatchDogError,
-    /// Crc in the message does not match the calculated Crc
-    CrcError,
+    /// A CheckMac or Verify command was properly received and executed, but
+    /// the result did not match the supplied MAC or signature.
+    #[error("mac or signature miscompare")]
+    Miscompare,
     /// Unknown or unhandled Ecc error
+    #[error("unknown status {0:#04x}")]
     Unknown(u8),
 }
 
@@ -144,13 +470,24 @@ macro_rules! put_cmd {
 
 impl EccCommand {
     pub fn info() -> Self {
-        Self::Info
+        Self::info_mode(InfoMode::Revision, 0)
+    }
+
+    pub fn info_mode(mode: InfoMode, param: u8) -> Self {
+        Self::Info { mode, param }
     }
 
     pub fn genkey(key_type: KeyType, slot: u8) -> Self {
         Self::GenKey { key_type, slot }
     }
 
+    pub fn genkey_digest(slot: u8, other_data: &[u8; 3]) -> Self {
+        Self::GenKeyDigest {
+            slot,
+            other_data: *other_data,
+        }
+    }
+
     pub fn read(is_32: bool, address: Address) -> Self {
         Self::Read { is_32, address }
     }
@@ -162,16 +499,63 @@ impl EccCommand {
         }
     }
 
+    /// An encrypted 32 byte write. `data` is the encrypted data followed by
+    /// the input MAC.
+    pub fn write_encrypted(address: Address, data: Bytes) -> Self {
+        Self::WriteEncrypted { address, data }
+    }
+
+    /// The param1 of an encrypted write, which is also covered by its MAC
+    pub(crate) fn write_encrypted_param1(address: &Address) -> u8 {
+        let mut param1 = ReadWriteParam(0);
+        param1.set_is_32(true);
+        param1.set_is_encrypted(true);
+        param1.set_address_zone(address.zone());
+        param1.into()
+    }
+
     pub fn lock(zone: Zone) -> Self {
-        Self::Lock { zone }
+        Self::Lock {
+            zone,
+            summary: None,
+        }
+    }
+
+    /// Locks the zone only if the CRC of its contents matches the given
+    /// summary.
+    pub fn lock_crc(zone: Zone, summary: u16) -> Self {
+        Self::Lock {
+            zone,
+            summary: Some(summary),
+        }
+    }
+
+    pub fn lock_slot(slot: u8) -> Self {
+        Self::LockSlot { slot }
     }
 
     pub fn random() -> Self {
-        Self::Random
+        Self::Random { seed_update: true }
+    }
+
+    pub fn random_no_seed_update() -> Self {
+        Self::Random { seed_update: false }
     }
 
     pub fn nonce(target: DataBuffer, data: Bytes) -> Self {
-        Self::Nonce { target, data }
+        Self::Nonce {
+            mode: NonceMode::Passthrough,
+            target,
+            data,
+        }
+    }
+
+    pub fn nonce_random(target: DataBuffer, num_in: &[u8]) -> Self {
+        Self::Nonce {
+            mode: NonceMode::Random,
+            target,
+            data: Bytes::copy_from_slice(num_in),
+        }
     }
 
     pub fn sign(source: DataBuffer, key_slot: u8) -> Self {
@@ -179,18 +563,169 @@ impl EccCommand {
     }
 
     pub fn ecdh(x: Bytes, y: Bytes, key_slot: u8) -> Self {
-        Self::Ecdh { key_slot, x, y }
+        Self::Ecdh {
+            key_slot,
+            x,
+            y,
+            to_tempkey: false,
+        }
+    }
+
+    pub fn ecdh_to_tempkey(x: Bytes, y: Bytes, key_slot: u8) -> Self {
+        Self::Ecdh {
+            key_slot,
+            x,
+            y,
+            to_tempkey: true,
+        }
+    }
+
+    pub fn verify_extern(source: DataBuffer, signature: Bytes, public_key: Bytes) -> Self {
+        Self::VerifyExtern {
+            source,
+            signature,
+            public_key,
+        }
+    }
+
+    pub fn verify_stored(source: DataBuffer, signature: Bytes, key_slot: u8) -> Self {
+        Self::VerifyStored {
+            source,
+            signature,
+            key_slot,
+        }
+    }
+
+    pub fn counter(counter_id: u8, increment: bool) -> Self {
+        Self::Counter {
+            counter_id,
+            increment,
+        }
+    }
+
+    pub fn sha(mode: ShaMode, data: &[u8]) -> Self {
+        Self::Sha {
+            mode,
+            data: Bytes::copy_from_slice(data),
+        }
+    }
+
+    pub fn gen_dig(zone: Zone, key_slot: u8, other_data: &[u8]) -> Self {
+        Self::GenDig {
+            zone,
+            key_slot,
+            other_data: Bytes::copy_from_slice(other_data),
+        }
+    }
+
+    pub fn derive_key(key_slot: u8) -> Self {
+        Self::DeriveKey { key_slot }
+    }
+
+    pub fn priv_write(key_slot: u8, data: Bytes) -> Self {
+        Self::PrivWrite { key_slot, data }
+    }
+
+    pub fn mac(mode: MacMode, key_slot: u8, challenge: &[u8]) -> Self {
+        Self::Mac {
+            mode,
+            key_slot,
+            challenge: Bytes::copy_from_slice(challenge),
+        }
+    }
+
+    pub fn check_mac(key_slot: u8, challenge: &[u8], response: &[u8], other_data: &[u8]) -> Self {
+        let mut data = BytesMut::with_capacity(challenge.len() + response.len() + other_data.len());
+        data.extend_from_slice(challenge);
+        data.extend_from_slice(response);
+        data.extend_from_slice(other_data);
+        Self::CheckMac {
+            key_slot,
+            data: data.freeze(),
+        }
+    }
+
+    pub fn self_test(tests: SelfTestFlags) -> Self {
+        Self::SelfTest { tests }
+    }
+
+    pub fn secure_boot(mode: SecureBootMode, digest: &[u8], signature: &[u8]) -> Self {
+        let mut data = BytesMut::with_capacity(digest.len() + signature.len());
+        data.extend_from_slice(digest);
+        data.extend_from_slice(signature);
+        Self::SecureBoot {
+            mode,
+            data: data.freeze(),
+        }
+    }
+
+    pub fn update_extra(target: UpdateExtraTarget, value: u8) -> Self {
+        Self::UpdateExtra { target, value }
+    }
+
+    pub fn aes(mode: AesMode, key_slot: u8, data: &[u8]) -> Self {
+        Self::Aes {
+            mode,
+            key_slot,
+            data: Bytes::copy_from_slice(data),
+        }
+    }
+
+    pub fn kdf(
+        algorithm: KdfAlgorithm,
+        source: KdfSource,
+        target: KdfTarget,
+        message: &[u8],
+    ) -> Self {
+        Self::Kdf {
+            algorithm,
+            source,
+            target,
+            message: Bytes::copy_from_slice(message),
+        }
+    }
+
+    pub fn opcode(&self) -> u8 {
+        match self {
+            Self::Info { .. } => ATCA_INFO,
+            Self::GenKey { .. } | Self::GenKeyDigest { .. } => ATCA_GENKEY,
+            Self::Read { .. } => ATCA_READ,
+            Self::Write { .. } | Self::WriteEncrypted { .. } => ATCA_WRITE,
+            Self::Lock { .. } | Self::LockSlot { .. } => ATCA_LOCK,
+            Self::Random { .. } => ATCA_RANDOM,
+            Self::Nonce { .. } => ATCA_NONCE,
+            Self::Sign { .. } => ATCA_SIGN,
+            Self::Ecdh { .. } => ATCA_ECDH,
+            Self::VerifyExtern { .. } | Self::VerifyStored { .. } => ATCA_VERIFY,
+            Self::Counter { .. } => ATCA_COUNTER,
+            Self::Sha { .. } => ATCA_SHA,
+            Self::GenDig { .. } => ATCA_GENDIG,
+            Self::DeriveKey { .. } => ATCA_DERIVE_KEY,
+            Self::PrivWrite { .. } => ATCA_PRIVWRITE,
+            Self::Mac { .. } => ATCA_MAC,
+            Self::CheckMac { .. } => ATCA_CHECKMAC,
+            Self::SelfTest { .. } => ATCA_SELF_TEST,
+            Self::SecureBoot { .. } => ATCA_SECUREBOOT,
+            Self::UpdateExtra { .. } => ATCA_UPDATE_EXTRA,
+            Self::Aes { .. } => ATCA_AES,
+            Self::Kdf { .. } => ATCA_KDF,
+        }
     }
 
     pub fn bytes_into(&self, bytes: &mut BytesMut) {
         bytes.put_u8(0x00);
         match self {
-            Self::Info => {
-                put_cmd!(bytes, ATCA_INFO, 0, 0);
+            Self::Info { mode, param } => {
+                put_cmd!(bytes, ATCA_INFO, *mode as u8, (*param as u16) << 8);
             }
             Self::GenKey { key_type, slot } => {
                 put_cmd!(bytes, ATCA_GENKEY, u8::from(key_type), (*slot as u16) << 8);
             }
+            Self::GenKeyDigest { slot, other_data } => {
+                // pubkey digest mode, no key is generated or returned
+                put_cmd!(bytes, ATCA_GENKEY, 0x10, (*slot as u16) << 8);
+                bytes.extend_from_slice(other_data)
+            }
             Self::Read { is_32, address } => {
                 let mut param1 = ReadWriteParam(0);
                 param1.set_is_32(*is_32);
@@ -204,21 +739,36 @@ impl EccCommand {
                 put_cmd!(bytes, ATCA_WRITE, u8::from(param1), u16::from(address));
                 bytes.extend_from_slice(data);
             }
-            Self::Lock { zone } => {
+            Self::WriteEncrypted { address, data } => {
+                let param1 = Self::write_encrypted_param1(address);
+                put_cmd!(bytes, ATCA_WRITE, param1, u16::from(address));
+                bytes.extend_from_slice(data);
+            }
+            Self::Lock { zone, summary } => {
                 let mut param1 = LockParam(0);
-                param1.set_crc(true);
+                param1.set_crc(summary.is_none());
                 param1.set_zone(match zone {
                     Zone::Config => 0x00,
                     Zone::Data => 0x01,
                 });
+                // the summary goes out in the little endian order of the
+                // chip's own crc
+                let summary = summary.unwrap_or(0).swap_bytes();
+                put_cmd!(bytes, ATCA_LOCK, u8::from(param1), summary);
+            }
+            Self::LockSlot { slot } => {
+                let mut param1 = LockParam(0);
+                param1.set_crc(true);
+                param1.set_zone(0x02);
+                param1.set_slot(*slot);
                 put_cmd!(bytes, ATCA_LOCK, u8::from(param1), 0);
             }
-            Self::Random => {
-                put_cmd!(bytes, ATCA_RANDOM, 0, 0);
+            Self::Random { seed_update } => {
+                put_cmd!(bytes, ATCA_RANDOM, !seed_update as u8, 0);
             }
-            Self::Nonce { target, data } => {
+            Self::Nonce { mode, target, data } => {
                 let mut param1 = NonceParam(0);
-                param1.set_mode(0x03); // pass-through only for now
+                param1.set_mode(mode.into());
                 param1.set_target(target.into());
                 param1.set_is_64(data.len() == 64);
                 put_cmd!(bytes, ATCA_NONCE, u8::from(param1), 0);
@@ -230,11 +780,148 @@ impl EccCommand {
                 param1.set_external(true);
                 put_cmd!(bytes, ATCA_SIGN, u8::from(param1), (*key_slot as u16) << 8);
             }
-            Self::Ecdh { x, y, key_slot } => {
-                put_cmd!(bytes, ATCA_ECDH, 0, *key_slot as u16);
+            Self::Ecdh {
+                x,
+                y,
+                key_slot,
+                to_tempkey,
+            } => {
+                // output target TempKey, the shared secret is not returned
+                let mode = if *to_tempkey { 0x08 } else { 0x00 };
+                put_cmd!(bytes, ATCA_ECDH, mode, (*key_slot as u16) << 8);
                 bytes.extend_from_slice(x);
                 bytes.extend_from_slice(y)
             }
+            Self::VerifyExtern {
+                source,
+                signature,
+                public_key,
+            } => {
+                let mut param1 = VerifyParam(0);
+                param1.set_mode(0x02); // external public key
+                param1.set_source(source.into());
+                // key id carries the curve type for external keys, P256 only
+                put_cmd!(bytes, ATCA_VERIFY, u8::from(param1), 0x0004 << 8);
+                bytes.extend_from_slice(signature);
+                bytes.extend_from_slice(public_key)
+            }
+            Self::VerifyStored {
+                source,
+                signature,
+                key_slot,
+            } => {
+                let mut param1 = VerifyParam(0);
+                param1.set_mode(0x00); // stored public key
+                param1.set_source(source.into());
+                put_cmd!(
+                    bytes,
+                    ATCA_VERIFY,
+                    u8::from(param1),
+                    (*key_slot as u16) << 8
+                );
+                bytes.extend_from_slice(signature)
+            }
+            Self::Counter {
+                counter_id,
+                increment,
+            } => {
+                put_cmd!(
+                    bytes,
+                    ATCA_COUNTER,
+                    u8::from(*increment),
+                    (*counter_id as u16) << 8
+                );
+            }
+            Self::Sha { mode, data } => {
+                let param2 = match mode {
+                    ShaMode::Start => 0,
+                    ShaMode::HmacStart { key_slot } => (*key_slot as u16) << 8,
+                    ShaMode::Update | ShaMode::End | ShaMode::HmacEnd => (data.len() as u16) << 8,
+                };
+                put_cmd!(bytes, ATCA_SHA, u8::from(mode), param2);
+                bytes.extend_from_slice(data)
+            }
+            Self::GenDig {
+                zone,
+                key_slot,
+                other_data,
+            } => {
+                let param1 = match zone {
+                    Zone::Config => 0x00,
+                    Zone::Data => 0x02,
+                };
+                put_cmd!(bytes, ATCA_GENDIG, param1, (*key_slot as u16) << 8);
+                bytes.extend_from_slice(other_data)
+            }
+            Self::DeriveKey { key_slot } => {
+                // TempKey source flag is expected to indicate a random nonce
+                put_cmd!(bytes, ATCA_DERIVE_KEY, 0x00, (*key_slot as u16) << 8);
+            }
+            Self::PrivWrite { key_slot, data } => {
+                // input data is always encrypted
+                put_cmd!(bytes, ATCA_PRIVWRITE, 0x40, (*key_slot as u16) << 8);
+                bytes.extend_from_slice(data)
+            }
+            Self::Mac {
+                mode,
+                key_slot,
+                challenge,
+            } => {
+                put_cmd!(bytes, ATCA_MAC, u8::from(mode), (*key_slot as u16) << 8);
+                bytes.extend_from_slice(challenge)
+            }
+            Self::CheckMac { key_slot, data } => {
+                // slot key and client challenge from the command data
+                put_cmd!(bytes, ATCA_CHECKMAC, 0x00, (*key_slot as u16) << 8);
+                bytes.extend_from_slice(data)
+            }
+            Self::SelfTest { tests } => {
+                put_cmd!(bytes, ATCA_SELF_TEST, tests.bits(), 0);
+            }
+            Self::SecureBoot { mode, data } => {
+                put_cmd!(bytes, ATCA_SECUREBOOT, *mode as u8, 0);
+                bytes.extend_from_slice(data)
+            }
+            Self::UpdateExtra { target, value } => {
+                put_cmd!(
+                    bytes,
+                    ATCA_UPDATE_EXTRA,
+                    u8::from(target),
+                    (*value as u16) << 8
+                );
+            }
+            Self::Aes {
+                mode,
+                key_slot,
+                data,
+            } => {
+                // always uses the first key block in the slot
+                put_cmd!(bytes, ATCA_AES, *mode as u8, (*key_slot as u16) << 8);
+                bytes.extend_from_slice(data)
+            }
+            Self::Kdf {
+                algorithm,
+                source,
+                target,
+                message,
+            } => {
+                let (source_mode, source_slot) = match source {
+                    KdfSource::TempKey => (0x00, 0),
+                    KdfSource::Slot(slot) => (0x02, *slot),
+                };
+                let (target_mode, target_slot) = match target {
+                    KdfTarget::TempKey => (0x00, 0),
+                    KdfTarget::Slot(slot) => (0x08, *slot),
+                    KdfTarget::Output => (0x10, 0),
+                };
+                let mode = *algorithm as u8 | target_mode | source_mode;
+                // key id is little endian: source slot, then target slot
+                let key_id = (source_slot as u16) << 8 | target_slot as u16;
+                put_cmd!(bytes, ATCA_KDF, mode, key_id);
+                // details: message is in the input data, length in the top byte
+                bytes.put_u32_le(0x02 | (message.len() as u32) << 24);
+                bytes.extend_from_slice(message)
+            }
         }
         bytes[1] = (bytes.len() + 1) as u8;
         bytes.put_u16_le(crc(&bytes[1..]))
@@ -243,9 +930,18 @@ impl EccCommand {
 
 impl EccResponse {
     pub fn from_bytes(buf: &[u8]) -> Result<Self> {
+        // the crc covers the count byte and the payload, status frames included
+        if buf.len() < ATCA_RSP_SIZE_MIN as usize {
+            return Err(Error::crc());
+        }
+        let (buf, mut buf_crc) = buf.split_at(buf.len() - 2);
+        if crc(buf) != buf_crc.get_u16_le() {
+            return Err(Error::crc());
+        }
         if buf[0] == ATCA_RSP_SIZE_MIN {
             match buf[1] {
                 CMD_STATUS_BYTE_SUCCESS => Ok(Self::Data(Bytes::new())),
+                CMD_STATUS_BYTE_MISCOMPARE => Ok(Self::Error(EccError::Miscompare)),
                 CMD_STATUS_BYTE_PARSE => Ok(Self::Error(EccError::ParseError)),
                 CMD_STATUS_BYTE_ECC => Ok(Self::Error(EccError::Fault)),
                 CMD_STATUS_BYTE_SELF_TEST => Ok(Self::Error(EccError::SelfTestError)),
@@ -255,24 +951,32 @@ impl EccResponse {
                 error => Ok(Self::Error(EccError::Unknown(error))),
             }
         } else {
-            let (buf, mut buf_crc) = buf.split_at(buf.len() - 2);
-            let expected = crc(buf);
-            let actual = buf_crc.get_u16_le();
-            if expected != actual {
-                return Ok(Self::Error(EccError::CrcError));
-            }
             Ok(Self::Data(Bytes::copy_from_slice(&buf[1..])))
         }
     }
 }
 
 impl EccError {
+    /// Returns the status byte the chip reported for this error, if any.
+    pub fn status(&self) -> Option<u8> {
+        match self {
+            Self::ParseError => Some(CMD_STATUS_BYTE_PARSE),
+            Self::Fault => Some(CMD_STATUS_BYTE_ECC),
+            Self::SelfTestError => Some(CMD_STATUS_BYTE_SELF_TEST),
+            Self::ExecError => Some(CMD_STATUS_BYTE_EXEC),
+            Self::CommsError => Some(CMD_STATUS_BYTE_COMM),
+            Self::WatchDogError => Some(CMD_STATUS_BYTE_WATCHDOG),
+            Self::Miscompare => Some(CMD_STATUS_BYTE_MISCOMPARE),
+            Self::Unknown(status) => Some(*status),
+        }
+    }
+
     pub fn is_recoverable(&self) -> bool {
-        !matches!(self, Self::ParseError | Self::ExecError)
+        !matches!(self, Self::ParseError | Self::ExecError | Self::Miscompare)
     }
 }
 
-fn crc(src: &[u8]) -> u16 {
+pub(crate) fn crc(src: &[u8]) -> u16 {
     const POLYNOM: u16 = 0x8005;
     let mut crc: u16 = 0x0000;
     let mut data_bit;
@@ -308,4 +1012,61 @@ mod tests {
         // assert encoding
         assert_eq!(&[0x03, 0x07, 0x30, 0x00, 0x00, 0x00, 0x03, 0x5D], &buf[..])
     }
+
+    #[test]
+    fn sha_end() {
+        let packet = EccCommand::sha(ShaMode::End, b"abc");
+        let mut buf = BytesMut::with_capacity(ATCA_CMD_SIZE_MAX as usize);
+        buf.put_u8(0x03);
+        packet.bytes_into(&mut buf);
+        // count covers header, data and crc; param2 is the data length
+        assert_eq!(
+            &[0x03, 0x0A, 0x47, 0x02, 0x03, 0x00, b'a', b'b', b'c'],
+            &buf[..9]
+        );
+        assert_eq!(crc(&buf[1..9]), (&buf[9..]).get_u16_le());
+    }
+
+    #[test]
+    fn mac_modes() {
+        assert_eq!(0x00, u8::from(&MacMode::Challenge));
+        assert_eq!(0x01, u8::from(&MacMode::TempKey));
+
+        let packet = EccCommand::mac(MacMode::TempKey, 3, &[]);
+        let mut buf = BytesMut::with_capacity(ATCA_CMD_SIZE_MAX as usize);
+        buf.put_u8(0x03);
+        packet.bytes_into(&mut buf);
+        assert_eq!(&[0x03, 0x07, 0x08, 0x01, 0x03, 0x00], &buf[..6]);
+    }
+
+    #[test]
+    fn lock_crc() {
+        let packet = EccCommand::lock_crc(Zone::Data, 0x1234);
+        let mut buf = BytesMut::with_capacity(ATCA_CMD_SIZE_MAX as usize);
+        buf.put_u8(0x03);
+        packet.bytes_into(&mut buf);
+        assert_eq!(&[0x03, 0x07, 0x17, 0x01, 0x34, 0x12], &buf[..6]);
+
+        let packet = EccCommand::lock(Zone::Data);
+        let mut buf = BytesMut::with_capacity(ATCA_CMD_SIZE_MAX as usize);
+        buf.put_u8(0x03);
+        packet.bytes_into(&mut buf);
+        assert_eq!(&[0x03, 0x07, 0x17, 0x81, 0x00, 0x00], &buf[..6]);
+    }
+
+    #[test]
+    fn response_crc_mismatch() {
+        assert!(matches!(
+            EccResponse::from_bytes(&[0x04, 0x00, 0x03, 0x40]),
+            Ok(EccResponse::Data(_))
+        ));
+        assert!(matches!(
+            EccResponse::from_bytes(&[0x04, 0x00, 0x03, 0x41]),
+            Err(Error::Crc)
+        ));
+        assert!(matches!(
+            EccResponse::from_bytes(&[0x07, 0x01, 0x02, 0x03, 0x04, 0x00, 0x00]),
+            Err(Error::Crc)
+        ));
+    }
 }
@@ -0,0 +1,562 @@
+//! Microchip's compressed certificate format (ATCACERT), which stores only
+//! the parts of an X.509 certificate that differ between devices in a 72
+//! byte data slot. The full certificate is rebuilt from a [`CertTemplate`]
+//! holding everything else.
+
+use crate::{
+    slot_size, transport::Transport, Ecc, Error, PublicKey, Result, SerialNumber, Signature,
+};
+use sha2::{Digest, Sha256};
+
+/// The size of a compressed certificate in a data slot
+pub const COMPRESSED_CERT_SIZE: usize = 72;
+
+/// The hour resolution date the compressed format stores. Minutes and seconds
+/// are always zero.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct CertDate {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+}
+
+impl CertDate {
+    /// The date of certificates without a well-defined expiration, from RFC
+    /// 5280. Its minutes and seconds are 59 instead of zero.
+    const NO_EXPIRATION: Self = Self {
+        year: 9999,
+        month: 12,
+        day: 31,
+        hour: 23,
+    };
+
+    fn years_later(&self, years: u16) -> Self {
+        Self {
+            year: self.year + years,
+            ..*self
+        }
+    }
+
+    /// Encodes the date as UTCTime (13 characters, years 1950 to 2049) or
+    /// GeneralizedTime (15 characters) text.
+    fn encode(&self, len: usize) -> Result<String> {
+        let time = match *self {
+            Self::NO_EXPIRATION => "235959Z".to_string(),
+            _ => format!("{:02}0000Z", self.hour),
+        };
+        match len {
+            13 if (1950..2050).contains(&self.year) => Ok(format!(
+                "{:02}{:02}{:02}{}",
+                self.year % 100,
+                self.month,
+                self.day,
+                time
+            )),
+            15 => Ok(format!(
+                "{:04}{:02}{:02}{}",
+                self.year, self.month, self.day, time
+            )),
+            _ => Err(Error::invalid_compressed_cert(
+                "date doesn't fit the template",
+            )),
+        }
+    }
+
+    /// Parses UTCTime or GeneralizedTime text, which has to be on the hour
+    /// unless it's the no expiration date.
+    fn decode(text: &[u8]) -> Result<Self> {
+        let invalid = || Error::invalid_compressed_cert("invalid certificate date");
+        let digits = |range: std::ops::Range<usize>| -> Result<u16> {
+            let text = text.get(range).ok_or_else(invalid)?;
+            if !text.iter().all(u8::is_ascii_digit) {
+                return Err(invalid());
+            }
+            Ok(text.iter().fold(0, |n, d| n * 10 + (d - b'0') as u16))
+        };
+        let (year, rest) = match text.len() {
+            13 => match digits(0..2)? {
+                year @ 0..=49 => (2000 + year, 2),
+                year => (1900 + year, 2),
+            },
+            15 => (digits(0..4)?, 4),
+            _ => return Err(invalid()),
+        };
+        let date = Self {
+            year,
+            month: digits(rest..rest + 2)? as u8,
+            day: digits(rest + 2..rest + 4)? as u8,
+            hour: digits(rest + 4..rest + 6)? as u8,
+        };
+        let time = &text[rest + 6..];
+        match (date, time) {
+            (Self::NO_EXPIRATION, b"5959Z") | (_, b"0000Z") => Ok(date),
+            _ => Err(Error::invalid_compressed_cert(
+                "certificate date isn't on the hour",
+            )),
+        }
+    }
+}
+
+/// Where the serial number of the certificate comes from
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum SnSource {
+    /// The serial number is part of the template
+    Stored = 0x0,
+    /// The serial number is stored outside the compressed certificate (not
+    /// supported)
+    StoredDynamic = 0x7,
+    /// The 9 byte device serial number
+    DeviceSn = 0x8,
+    /// The 2 byte signer id
+    SignerId = 0x9,
+    /// SHA256 of the public key and the encoded dates, made positive with
+    /// the second highest bit set
+    PubKeyHash = 0xA,
+    /// SHA256 of the device serial number and the encoded dates, made
+    /// positive with the second highest bit set
+    DeviceSnHash = 0xB,
+    /// Like [`PubKeyHash`](Self::PubKeyHash) but only made positive
+    PubKeyHashPos = 0xC,
+    /// Like [`DeviceSnHash`](Self::DeviceSnHash) but only made positive
+    DeviceSnHashPos = 0xD,
+    /// Like [`PubKeyHash`](Self::PubKeyHash) but used as is
+    PubKeyHashRaw = 0xE,
+    /// Like [`DeviceSnHash`](Self::DeviceSnHash) but used as is
+    DeviceSnHashRaw = 0xF,
+}
+
+impl SnSource {
+    fn from_nibble(v: u8) -> Result<Self> {
+        match v {
+            0x0 => Ok(Self::Stored),
+            0x7 => Ok(Self::StoredDynamic),
+            0x8 => Ok(Self::DeviceSn),
+            0x9 => Ok(Self::SignerId),
+            0xA => Ok(Self::PubKeyHash),
+            0xB => Ok(Self::DeviceSnHash),
+            0xC => Ok(Self::PubKeyHashPos),
+            0xD => Ok(Self::DeviceSnHashPos),
+            0xE => Ok(Self::PubKeyHashRaw),
+            0xF => Ok(Self::DeviceSnHashRaw),
+            _ => Err(Error::invalid_compressed_cert(
+                "unknown serial number source",
+            )),
+        }
+    }
+}
+
+/// A compressed certificate as stored in a data slot:
+///
+/// | bytes  | content                                                    |
+/// |--------|------------------------------------------------------------|
+/// | 0..64  | signature `r \|\| s`                                       |
+/// | 64..67 | issue year, month, day, hour and expire years, 5/4/5/5/5 bits |
+/// | 67..69 | signer id, big endian                                      |
+/// | 69     | template id (high nibble), chain id (low nibble)           |
+/// | 70     | serial number source (high nibble), format version (low)   |
+/// | 71     | reserved                                                   |
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct CompressedCert {
+    pub signature: Signature,
+    pub issue_date: CertDate,
+    /// The validity in years, zero for no well-defined expiration
+    pub expire_years: u8,
+    pub signer_id: u16,
+    pub template_id: u8,
+    pub chain_id: u8,
+    pub sn_source: SnSource,
+}
+
+impl CompressedCert {
+    /// Parses the 72 byte slot layout. Only format version 0 is defined.
+    pub fn from_slot_bytes(v: &[u8]) -> Result<Self> {
+        if v.len() != COMPRESSED_CERT_SIZE {
+            return Err(Error::invalid_length(COMPRESSED_CERT_SIZE, v.len()));
+        }
+        if v[70] & 0x0F != 0 {
+            return Err(Error::invalid_compressed_cert("unknown format version"));
+        }
+        let mut signature = [0u8; 64];
+        signature.copy_from_slice(&v[..64]);
+        let issue_date = CertDate {
+            year: 2000 + (v[64] >> 3) as u16,
+            month: ((v[64] & 0x07) << 1) | (v[65] >> 7),
+            day: (v[65] >> 2) & 0x1F,
+            hour: ((v[65] & 0x03) << 3) | (v[66] >> 5),
+        };
+        if !(1..=12).contains(&issue_date.month)
+            || !(1..=31).contains(&issue_date.day)
+            || issue_date.hour > 23
+        {
+            return Err(Error::invalid_compressed_cert("invalid issue date"));
+        }
+        Ok(Self {
+            signature: Signature(signature),
+            issue_date,
+            expire_years: v[66] & 0x1F,
+            signer_id: u16::from_be_bytes([v[67], v[68]]),
+            template_id: v[69] >> 4,
+            chain_id: v[69] & 0x0F,
+            sn_source: SnSource::from_nibble(v[70] >> 4)?,
+        })
+    }
+
+    /// Compresses a DER encoded certificate with the layout of the template.
+    /// The dates have to be on the hour with the issue year between 2000 and
+    /// 2031, and the expire date a whole number of years after the issue date
+    /// or the no expiration date of RFC 5280.
+    pub fn from_x509(cert: &[u8], template: &CertTemplate) -> Result<Self> {
+        let element = |element: &CertElement| {
+            cert.get(element.offset..element.offset + element.count)
+                .ok_or_else(|| Error::invalid_compressed_cert("element outside the certificate"))
+        };
+        let signature = match cert.get(template.signature_offset..) {
+            Some([0x03, len, 0x00, signature @ ..]) if *len as usize == signature.len() + 1 => {
+                Signature::from_der(signature)?
+            }
+            _ => return Err(Error::invalid_compressed_cert("invalid signature")),
+        };
+        let issue_date = CertDate::decode(element(&template.issue_date)?)?;
+        if !(2000..=2031).contains(&issue_date.year) {
+            return Err(Error::invalid_compressed_cert("issue year out of range"));
+        }
+        let expire_date = CertDate::decode(element(&template.expire_date)?)?;
+        let years = expire_date
+            .year
+            .checked_sub(issue_date.year)
+            .filter(|years| (1..=31).contains(years));
+        let expire_years = match years {
+            _ if expire_date == CertDate::NO_EXPIRATION => 0,
+            Some(years) if expire_date == issue_date.years_later(years) => years as u8,
+            _ => {
+                return Err(Error::invalid_compressed_cert(
+                    "expire date isn't whole years after the issue date",
+                ))
+            }
+        };
+        let signer_id = match &template.signer_id {
+            Some(signer_id) => std::str::from_utf8(element(signer_id)?)
+                .ok()
+                .and_then(|hex| u16::from_str_radix(hex, 16).ok())
+                .ok_or_else(|| Error::invalid_compressed_cert("invalid signer id"))?,
+            None => 0,
+        };
+        Ok(Self {
+            signature,
+            issue_date,
+            expire_years,
+            signer_id,
+            template_id: template.template_id,
+            chain_id: template.chain_id,
+            sn_source: template.sn_source,
+        })
+    }
+
+    /// Returns the 72 byte slot layout of the certificate.
+    pub fn to_slot_bytes(&self) -> [u8; COMPRESSED_CERT_SIZE] {
+        let mut result = [0u8; COMPRESSED_CERT_SIZE];
+        result[..64].copy_from_slice(&self.signature.0);
+        result[64..67].copy_from_slice(&self.encoded_dates());
+        result[67..69].copy_from_slice(&self.signer_id.to_be_bytes());
+        result[69] = (self.template_id << 4) | (self.chain_id & 0x0F);
+        result[70] = (self.sn_source as u8) << 4;
+        result
+    }
+
+    /// The expiration date, the issue date moved by the validity in years.
+    pub fn expire_date(&self) -> CertDate {
+        match self.expire_years {
+            0 => CertDate::NO_EXPIRATION,
+            years => self.issue_date.years_later(years as u16),
+        }
+    }
+
+    /// Rebuilds the DER encoded certificate from the template, with the
+    /// public key of the certified key. The device serial number is only
+    /// needed for the serial number sources derived from it.
+    pub fn to_der(
+        &self,
+        template: &CertTemplate,
+        public_key: &PublicKey,
+        device_sn: Option<&SerialNumber>,
+    ) -> Result<Vec<u8>> {
+        if (template.template_id, template.chain_id) != (self.template_id, self.chain_id) {
+            return Err(Error::invalid_compressed_cert("template doesn't match"));
+        }
+        let der = &template.der;
+        // the certificate sequence has to have a two byte length
+        if der.len() < 4 || der[..2] != [0x30, 0x82] || template.signature_offset > der.len() {
+            return Err(Error::invalid_compressed_cert("invalid template"));
+        }
+        let mut result = der[..template.signature_offset].to_vec();
+
+        set_element(&mut result, &template.public_key, &public_key.to_bytes())?;
+        let issue_date = self.issue_date.encode(template.issue_date.count)?;
+        set_element(&mut result, &template.issue_date, issue_date.as_bytes())?;
+        let expire_date = self.expire_date().encode(template.expire_date.count)?;
+        set_element(&mut result, &template.expire_date, expire_date.as_bytes())?;
+        if let Some(element) = &template.signer_id {
+            let signer_id = format!("{:04X}", self.signer_id);
+            set_element(&mut result, element, signer_id.as_bytes())?;
+        }
+        if let Some(element) = &template.serial_number {
+            let serial_number = self.serial_number(element.count, public_key, device_sn)?;
+            set_element(&mut result, element, &serial_number)?;
+        }
+
+        let signature = self.signature.to_der();
+        result.extend_from_slice(&[0x03, signature.len() as u8 + 1, 0x00]);
+        result.extend_from_slice(&signature);
+        let len = (result.len() - 4) as u16;
+        result[2..4].copy_from_slice(&len.to_be_bytes());
+        Ok(result)
+    }
+
+    fn encoded_dates(&self) -> [u8; 3] {
+        let date = &self.issue_date;
+        let year = (date.year - 2000) as u8;
+        [
+            (year << 3) | (date.month >> 1),
+            (date.month << 7) | (date.day << 2) | (date.hour >> 3),
+            (date.hour << 5) | (self.expire_years & 0x1F),
+        ]
+    }
+
+    /// The serial number of the given size from the serial number source.
+    /// Stored serial numbers are left as they are in the template.
+    fn serial_number(
+        &self,
+        len: usize,
+        public_key: &PublicKey,
+        device_sn: Option<&SerialNumber>,
+    ) -> Result<Vec<u8>> {
+        let device_sn = || {
+            device_sn.ok_or_else(|| Error::invalid_compressed_cert("device serial number required"))
+        };
+        let hash = |data: &[u8]| {
+            let mut hasher = Sha256::new();
+            hasher.update(data);
+            hasher.update(self.encoded_dates());
+            hasher.finalize()
+        };
+        let mut result = match self.sn_source {
+            SnSource::DeviceSn => device_sn()?.0.to_vec(),
+            SnSource::SignerId => self.signer_id.to_be_bytes().to_vec(),
+            SnSource::PubKeyHash | SnSource::PubKeyHashPos | SnSource::PubKeyHashRaw => {
+                hash(&public_key.to_bytes()).to_vec()
+            }
+            SnSource::DeviceSnHash | SnSource::DeviceSnHashPos | SnSource::DeviceSnHashRaw => {
+                hash(&device_sn()?.0).to_vec()
+            }
+            SnSource::Stored | SnSource::StoredDynamic => {
+                return Err(Error::invalid_compressed_cert(
+                    "serial number source needs no template element",
+                ))
+            }
+        };
+        if result.len() < len {
+            return Err(Error::invalid_compressed_cert(
+                "serial number doesn't fit the template",
+            ));
+        }
+        result.truncate(len);
+        match self.sn_source {
+            SnSource::PubKeyHash | SnSource::DeviceSnHash => result[0] = (result[0] & 0x7F) | 0x40,
+            SnSource::PubKeyHashPos | SnSource::DeviceSnHashPos => result[0] &= 0x7F,
+            _ => (),
+        }
+        Ok(result)
+    }
+}
+
+/// A range of bytes in a certificate template
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct CertElement {
+    pub offset: usize,
+    pub count: usize,
+}
+
+/// The template of a compressed certificate: a complete DER certificate of
+/// the same layout and where the per device elements go in it. Elements are
+/// replaced in place so they have to keep their size.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct CertTemplate {
+    pub template_id: u8,
+    pub chain_id: u8,
+    /// Where the serial number comes from, stored with the compressed
+    /// certificate
+    pub sn_source: SnSource,
+    pub der: Vec<u8>,
+    /// The 64 byte `x || y` point in the subject public key bit string
+    pub public_key: CertElement,
+    /// The text of the notBefore time, 13 bytes for UTCTime or 15 bytes for
+    /// GeneralizedTime
+    pub issue_date: CertElement,
+    /// The text of the notAfter time
+    pub expire_date: CertElement,
+    /// Where the signer id goes as 4 upper case hex digits, usually in the
+    /// issuer or subject common name
+    pub signer_id: Option<CertElement>,
+    /// The serial number integer contents, for the sources that generate
+    /// the serial number
+    pub serial_number: Option<CertElement>,
+    /// The offset of the signature bit string at the end of the certificate,
+    /// which is replaced with the signature of the compressed certificate
+    pub signature_offset: usize,
+}
+
+fn set_element(der: &mut [u8], element: &CertElement, data: &[u8]) -> Result {
+    if data.len() != element.count || element.offset + element.count > der.len() {
+        return Err(Error::invalid_compressed_cert(
+            "element doesn't fit the template",
+        ));
+    }
+    der[element.offset..element.offset + element.count].copy_from_slice(data);
+    Ok(())
+}
+
+impl<T: Transport> Ecc<T> {
+    /// Reads a compressed certificate from the given data slot. Only slots 8
+    /// to 15 are large enough to hold one.
+    pub fn read_compressed_cert(&mut self, slot: u8) -> Result<CompressedCert> {
+        if slot_size(slot)? < COMPRESSED_CERT_SIZE {
+            return Err(Error::invalid_slot(slot));
+        }
+        CompressedCert::from_slot_bytes(&self.read_bytes(slot, 0, COMPRESSED_CERT_SIZE)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::TryFrom;
+
+    fn find(haystack: &[u8], needle: &[u8]) -> usize {
+        haystack
+            .windows(needle.len())
+            .position(|w| w == needle)
+            .expect("element")
+    }
+
+    /// The end of the DER element at the offset
+    fn element_end(der: &[u8], offset: usize) -> usize {
+        match der[offset + 1] {
+            0x81 => offset + 3 + der[offset + 2] as usize,
+            0x82 => offset + 4 + u16::from_be_bytes([der[offset + 2], der[offset + 3]]) as usize,
+            len => offset + 2 + len as usize,
+        }
+    }
+
+    #[test]
+    fn slot_layout() {
+        let mut raw = [0u8; COMPRESSED_CERT_SIZE];
+        raw[..64].copy_from_slice(&[0x11; 64]);
+        // 2024-05-01 10:00 valid for 10 years
+        raw[64..67].copy_from_slice(&[0xC2, 0x85, 0x4A]);
+        raw[67..].copy_from_slice(&[0x1A, 0x2B, 0x32, 0xA0, 0x00]);
+        let cert = CompressedCert::from_slot_bytes(&raw).expect("compressed cert");
+        assert_eq!(
+            CertDate {
+                year: 2024,
+                month: 5,
+                day: 1,
+                hour: 10,
+            },
+            cert.issue_date
+        );
+        assert_eq!(2034, cert.expire_date().year);
+        assert_eq!(0x1A2B, cert.signer_id);
+        assert_eq!((3, 2), (cert.template_id, cert.chain_id));
+        assert_eq!(SnSource::PubKeyHash, cert.sn_source);
+        assert_eq!(raw, cert.to_slot_bytes());
+
+        raw[70] = 0xA1;
+        assert!(CompressedCert::from_slot_bytes(&raw).is_err());
+    }
+
+    #[test]
+    fn rebuilds_certificate() {
+        let key_pair = rcgen::KeyPair::generate().expect("key pair");
+        let public_key = PublicKey::try_from(&key_pair.public_key_raw()[1..]).expect("public key");
+        let cert = CompressedCert {
+            signature: Signature([0; 64]),
+            issue_date: CertDate {
+                year: 2024,
+                month: 5,
+                day: 1,
+                hour: 0,
+            },
+            expire_years: 10,
+            signer_id: 0x1A2B,
+            template_id: 1,
+            chain_id: 0,
+            sn_source: SnSource::PubKeyHash,
+        };
+        let serial_number = cert
+            .serial_number(16, &public_key, None)
+            .expect("serial number");
+
+        let mut params = rcgen::CertificateParams::new(vec!["device".to_string()]).expect("params");
+        params.not_before = rcgen::date_time_ymd(2024, 5, 1);
+        params.not_after = rcgen::date_time_ymd(2034, 5, 1);
+        params.serial_number = Some(rcgen::SerialNumber::from(serial_number.clone()));
+        params
+            .distinguished_name
+            .push(rcgen::DnType::CommonName, "Example Signer 1A2B");
+        let der = params
+            .self_signed(&key_pair)
+            .expect("certificate")
+            .der()
+            .to_vec();
+
+        // the signature follows the tbs certificate and its algorithm
+        let signature_offset = element_end(&der, element_end(&der, 4));
+        let at = |data: &[u8]| CertElement {
+            offset: find(&der, data),
+            count: data.len(),
+        };
+        let template = CertTemplate {
+            template_id: 1,
+            chain_id: 0,
+            sn_source: SnSource::PubKeyHash,
+            public_key: at(&public_key.to_bytes()),
+            issue_date: at(b"240501000000Z"),
+            expire_date: at(b"340501000000Z"),
+            signer_id: Some(at(b"1A2B")),
+            serial_number: Some(at(&serial_number)),
+            signature_offset,
+            der: der.clone(),
+        };
+        // clear the per device elements, only the layout has to match
+        let mut blank = template.clone();
+        for element in [
+            template.public_key,
+            template.issue_date,
+            template.expire_date,
+            template.signer_id.expect("signer id"),
+            template.serial_number.expect("serial number"),
+        ] {
+            blank.der[element.offset..element.offset + element.count].fill(0);
+        }
+
+        // compress the certificate, store it and rebuild it
+        let compressed = CompressedCert::from_x509(&der, &template).expect("compressed cert");
+        assert_eq!(
+            CompressedCert {
+                signature: Signature::from_der(&der[signature_offset + 3..]).expect("signature"),
+                ..cert
+            },
+            compressed
+        );
+        let mut cert =
+            CompressedCert::from_slot_bytes(&compressed.to_slot_bytes()).expect("compressed cert");
+        assert_eq!(
+            der,
+            cert.to_der(&blank, &public_key, None).expect("certificate")
+        );
+
+        cert.template_id = 2;
+        assert!(cert.to_der(&blank, &public_key, None).is_err());
+    }
+}
@@ -0,0 +1,652 @@
+use crate::{
+    ecc::slot_size, Error, KeyConfig, KeyConfigType, Result, SerialNumber, SlotConfig, MAX_SLOT,
+};
+use bytes::Buf;
+#[cfg(feature = "serde")]
+use serde_derive::Deserialize;
+use serde_derive::Serialize;
+use std::{convert::TryFrom, fmt};
+
+pub(crate) const CONFIG_ZONE_SIZE: usize = 128;
+
+/// The parsed 128 byte config zone of an ECC608.
+///
+/// With the `serde` feature the whole zone can be kept as a golden config
+/// file. The read only serial number and revision are serialized but may be
+/// left out when deserializing, since
+/// [`Ecc::write_config`](crate::Ecc::write_config) never writes them.
+#[derive(Serialize, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize))]
+pub struct ConfigZone {
+    #[cfg_attr(feature = "serde", serde(default = "placeholder_serial_number"))]
+    pub serial_number: SerialNumber,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub revision: [u8; 4],
+    pub aes_enable: u8,
+    pub i2c_enable: u8,
+    /// The 8 bit I2C address, the 7 bit address shifted left by one
+    pub i2c_address: u8,
+    pub count_match: u8,
+    pub chip_mode: u8,
+    pub slot_configs: [SlotConfig; 16],
+    /// The two monotonic counters in their raw encoding
+    pub counters: [[u8; 8]; 2],
+    pub use_lock: u8,
+    pub volatile_key_permission: u8,
+    pub secure_boot: u16,
+    pub kdf_iv_loc: u8,
+    pub kdf_iv_str: u16,
+    pub user_extra: u8,
+    pub user_extra_add: u8,
+    /// `0x00` once the data zone is locked, `0x55` before
+    pub lock_value: u8,
+    /// `0x00` once the config zone is locked, `0x55` before
+    pub lock_config: u8,
+    /// A bit for each slot that is cleared once the slot is locked
+    pub slot_locked: u16,
+    pub chip_options: u16,
+    pub x509_format: [u8; 4],
+    pub key_configs: [KeyConfig; 16],
+}
+
+impl ConfigZone {
+    pub fn config_locked(&self) -> bool {
+        self.lock_config != 0x55
+    }
+
+    pub fn data_locked(&self) -> bool {
+        self.lock_value != 0x55
+    }
+
+    pub fn slot_locked(&self, slot: u8) -> bool {
+        slot <= MAX_SLOT && self.slot_locked & (1 << slot) == 0
+    }
+
+    /// Returns the configuration, lock state and size of every slot.
+    pub fn slots(&self) -> Vec<SlotInfo> {
+        (0..=MAX_SLOT)
+            .map(|slot| SlotInfo {
+                slot,
+                slot_config: self.slot_configs[slot as usize],
+                key_config: self.key_configs[slot as usize],
+                locked: self.slot_locked(slot),
+                size: slot_size(slot).unwrap_or_default(),
+            })
+            .collect()
+    }
+
+    /// Decodes the optional features enabled by the AESEnable and
+    /// ChipOptions bytes.
+    pub fn features(&self) -> Features {
+        let bit = |n: u16| self.chip_options & (1 << n) != 0;
+        Features {
+            aes_enabled: self.aes_enable & 1 != 0,
+            power_on_self_test: bit(0),
+            io_protection_enabled: bit(1),
+            kdf_aes_enabled: bit(2),
+            ecdh_protection: (self.chip_options >> 8 & 0x3) as u8,
+            kdf_protection: (self.chip_options >> 10 & 0x3) as u8,
+            io_protection_key: (self.chip_options >> 12) as u8,
+        }
+    }
+
+    /// Compares this expected config against the given actual config and
+    /// returns the fields that differ. The serial number and revision are
+    /// unique to every device and not compared.
+    pub fn diff(&self, actual: &ConfigZone) -> Vec<ConfigDiff> {
+        self.fields()
+            .into_iter()
+            .zip(actual.fields())
+            .filter(|(expected, actual)| expected.2 != actual.2)
+            .map(|((slot, field, expected), (_, _, actual))| ConfigDiff {
+                slot,
+                field,
+                expected,
+                actual,
+            })
+            .collect()
+    }
+
+    /// All comparable fields, in a fixed order
+    fn fields(&self) -> Vec<(Option<u8>, &'static str, u64)> {
+        let mut fields = vec![
+            (None, "aes_enable", self.aes_enable as u64),
+            (None, "i2c_enable", self.i2c_enable as u64),
+            (None, "i2c_address", self.i2c_address as u64),
+            (None, "count_match", self.count_match as u64),
+            (None, "chip_mode", self.chip_mode as u64),
+            (None, "counter0", u64::from_be_bytes(self.counters[0])),
+            (None, "counter1", u64::from_be_bytes(self.counters[1])),
+            (None, "use_lock", self.use_lock as u64),
+            (
+                None,
+                "volatile_key_permission",
+                self.volatile_key_permission as u64,
+            ),
+            (None, "secure_boot", self.secure_boot as u64),
+            (None, "kdf_iv_loc", self.kdf_iv_loc as u64),
+            (None, "kdf_iv_str", self.kdf_iv_str as u64),
+            (None, "user_extra", self.user_extra as u64),
+            (None, "user_extra_add", self.user_extra_add as u64),
+            (None, "lock_value", self.lock_value as u64),
+            (None, "lock_config", self.lock_config as u64),
+            (None, "slot_locked", self.slot_locked as u64),
+            (None, "chip_options", self.chip_options as u64),
+            (
+                None,
+                "x509_format",
+                u32::from_be_bytes(self.x509_format) as u64,
+            ),
+        ];
+        for slot in 0..=MAX_SLOT {
+            fields.push((
+                Some(slot),
+                "slot_config",
+                u16::from(&self.slot_configs[slot as usize]) as u64,
+            ));
+            fields.push((
+                Some(slot),
+                "key_config",
+                u16::from(&self.key_configs[slot as usize]) as u64,
+            ));
+        }
+        fields
+    }
+
+    /// Checks the config for combinations that would leave the device or
+    /// some of its slots unusable or insecure once the config zone is locked,
+    /// and returns all of the problems found.
+    pub fn validate(&self) -> std::result::Result<(), Vec<ConfigError>> {
+        let mut errors = vec![];
+        let aes_enabled = self.aes_enable & 1 != 0;
+        // the ECC508 has no AES engine, ECC608 revisions start with 0x60
+        if aes_enabled && self.revision[2] != 0x60 {
+            errors.push(ConfigError {
+                slot: None,
+                reason: "AES is enabled but the device has no AES support",
+            });
+        }
+        for slot in 0..=MAX_SLOT {
+            let slot_config = &self.slot_configs[slot as usize];
+            let key_config = &self.key_configs[slot as usize];
+            let mut error = |reason| {
+                errors.push(ConfigError {
+                    slot: Some(slot),
+                    reason,
+                })
+            };
+            if let Err(Error::InvalidSlotConfig(_, reason)) = key_config.validate(slot) {
+                error(reason);
+            }
+            if key_config.private() {
+                if !slot_config.secret() {
+                    error("private key slot allows clear reads");
+                }
+                let write_config = u16::from(slot_config) >> 4 & 0xF;
+                // without GenKey or PrivWrite a key can never be created
+                if write_config & 0x6 == 0 {
+                    error("private key slot can't be written by GenKey or PrivWrite");
+                }
+            }
+            if key_config.key_type() == KeyConfigType::Aes && !aes_enabled {
+                error("AES key slot but AES is disabled");
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Returns a human readable report of the config, see the `Display`
+    /// implementation.
+    pub fn to_report(&self) -> String {
+        self.to_string()
+    }
+
+    /// Returns the 128 byte config zone encoding. Reserved bytes are zero.
+    pub fn to_bytes(&self) -> [u8; CONFIG_ZONE_SIZE] {
+        let mut result = [0u8; CONFIG_ZONE_SIZE];
+        let serial_number = &self.serial_number.0;
+        result[0..4].copy_from_slice(&serial_number[..4]);
+        result[4..8].copy_from_slice(&self.revision);
+        result[8..13].copy_from_slice(&serial_number[4..]);
+        result[13] = self.aes_enable;
+        result[14] = self.i2c_enable;
+        result[16] = self.i2c_address;
+        result[18] = self.count_match;
+        result[19] = self.chip_mode;
+        for (slot, config) in self.slot_configs.iter().enumerate() {
+            let offset = 20 + slot * 2;
+            result[offset..offset + 2].copy_from_slice(&u16::from(config).to_be_bytes());
+        }
+        result[52..60].copy_from_slice(&self.counters[0]);
+        result[60..68].copy_from_slice(&self.counters[1]);
+        result[68] = self.use_lock;
+        result[69] = self.volatile_key_permission;
+        result[70..72].copy_from_slice(&self.secure_boot.to_le_bytes());
+        result[72] = self.kdf_iv_loc;
+        result[73..75].copy_from_slice(&self.kdf_iv_str.to_le_bytes());
+        result[84] = self.user_extra;
+        result[85] = self.user_extra_add;
+        result[86] = self.lock_value;
+        result[87] = self.lock_config;
+        result[88..90].copy_from_slice(&self.slot_locked.to_le_bytes());
+        result[90..92].copy_from_slice(&self.chip_options.to_le_bytes());
+        result[92..96].copy_from_slice(&self.x509_format);
+        for (slot, config) in self.key_configs.iter().enumerate() {
+            let offset = 96 + slot * 2;
+            result[offset..offset + 2].copy_from_slice(&u16::from(config).to_be_bytes());
+        }
+        result
+    }
+}
+
+/// A slot of the config zone, see [`ConfigZone::slots`].
+#[derive(Debug, PartialEq)]
+pub struct SlotInfo {
+    pub slot: u8,
+    pub slot_config: SlotConfig,
+    pub key_config: KeyConfig,
+    /// Whether the slot is individually locked
+    pub locked: bool,
+    /// The size of the slot in bytes
+    pub size: usize,
+}
+
+/// The optional features of the chip as configured, see
+/// [`ConfigZone::features`]. The HKDF and PRF modes of the KDF command are
+/// always available, only its AES mode has to be enabled.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Features {
+    /// The AES command and the AES modes of KDF are enabled
+    pub aes_enabled: bool,
+    /// The self tests run at power up
+    pub power_on_self_test: bool,
+    /// Outputs are encrypted with the IO protection key where the
+    /// protection levels allow it
+    pub io_protection_enabled: bool,
+    /// The AES mode of the KDF command is allowed
+    pub kdf_aes_enabled: bool,
+    /// The ECDHProt bits: 0 output in the clear, 1 encrypted output, 2 only
+    /// output to TempKey or a slot
+    pub ecdh_protection: u8,
+    /// The KDFProt bits, with the same levels as `ecdh_protection`
+    pub kdf_protection: u8,
+    /// The slot of the IO protection key
+    pub io_protection_key: u8,
+}
+
+/// A field that differs between two configs, see [`ConfigZone::diff`].
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ConfigDiff {
+    /// The slot of per slot fields like the slot and key configs
+    pub slot: Option<u8>,
+    pub field: &'static str,
+    /// The raw value of the field, counters and the X509 format are read as
+    /// big endian numbers
+    pub expected: u64,
+    pub actual: u64,
+}
+
+/// A problem found by [`ConfigZone::validate`].
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ConfigError {
+    /// The slot the problem is in, if it is specific to one
+    pub slot: Option<u8>,
+    pub reason: &'static str,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.slot {
+            Some(slot) => write!(f, "slot {}: {}", slot, self.reason),
+            None => f.write_str(self.reason),
+        }
+    }
+}
+
+/// Lists the device info, the lock state of the zones and the decoded slot
+/// and key config of every slot.
+impl fmt::Display for ConfigZone {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let locked = |locked: bool| if locked { "locked" } else { "unlocked" };
+        writeln!(f, "serial number: {}", self.serial_number)?;
+        writeln!(f, "revision: {:02x?}", self.revision)?;
+        writeln!(f, "i2c address: {:#04x}", self.i2c_address >> 1)?;
+        writeln!(f, "chip mode: {:#04x}", self.chip_mode)?;
+        writeln!(f, "config zone: {}", locked(self.config_locked()))?;
+        writeln!(f, "data zone: {}", locked(self.data_locked()))?;
+        for slot in 0..=MAX_SLOT {
+            let slot_config = &self.slot_configs[slot as usize];
+            let key_config = &self.key_configs[slot as usize];
+            writeln!(f, "slot {:2}: {}", slot, locked(self.slot_locked(slot)))?;
+            writeln!(f, "  slot config: {}", slot_config)?;
+            writeln!(f, "  key config: {}", key_config)?;
+        }
+        Ok(())
+    }
+}
+
+/// The names of the set flags, each preceded by a space
+pub(crate) fn flags(flags: &[(&str, bool)]) -> String {
+    flags
+        .iter()
+        .filter(|(_, set)| *set)
+        .map(|(name, _)| format!(" {}", name))
+        .collect()
+}
+
+impl TryFrom<&[u8]> for ConfigZone {
+    type Error = Error;
+
+    fn try_from(v: &[u8]) -> Result<Self> {
+        if v.len() != CONFIG_ZONE_SIZE {
+            return Err(Error::invalid_length(CONFIG_ZONE_SIZE, v.len()));
+        }
+        let mut serial_number = [0u8; 9];
+        serial_number[..4].copy_from_slice(&v[0..4]);
+        serial_number[4..].copy_from_slice(&v[8..13]);
+        let mut revision = [0u8; 4];
+        revision.copy_from_slice(&v[4..8]);
+        let mut counters = [[0u8; 8]; 2];
+        counters[0].copy_from_slice(&v[52..60]);
+        counters[1].copy_from_slice(&v[60..68]);
+        let mut x509_format = [0u8; 4];
+        x509_format.copy_from_slice(&v[92..96]);
+        Ok(Self {
+            serial_number: SerialNumber::try_from(&serial_number[..])?,
+            revision,
+            aes_enable: v[13],
+            i2c_enable: v[14],
+            i2c_address: v[16],
+            count_match: v[18],
+            chip_mode: v[19],
+            slot_configs: std::array::from_fn(|slot| SlotConfig::from(&v[20 + slot * 2..])),
+            counters,
+            use_lock: v[68],
+            volatile_key_permission: v[69],
+            secure_boot: (&v[70..72]).get_u16_le(),
+            kdf_iv_loc: v[72],
+            kdf_iv_str: (&v[73..75]).get_u16_le(),
+            user_extra: v[84],
+            user_extra_add: v[85],
+            lock_value: v[86],
+            lock_config: v[87],
+            slot_locked: (&v[88..90]).get_u16_le(),
+            chip_options: (&v[90..92]).get_u16_le(),
+            x509_format,
+            key_configs: std::array::from_fn(|slot| KeyConfig::from(&v[96 + slot * 2..])),
+        })
+    }
+}
+
+/// Builds a [`ConfigZone`] to provision a device with, starting from all
+/// slots unlocked and set up to generate and store ECC private keys.
+///
+/// The serial number and revision of the built config are placeholders,
+/// [`Ecc::write_config`](crate::Ecc::write_config) skips those read only
+/// bytes.
+pub struct ConfigZoneBuilder {
+    config: ConfigZone,
+    invalid_slot: Option<u8>,
+    invalid_address: bool,
+}
+
+impl Default for ConfigZoneBuilder {
+    fn default() -> Self {
+        Self {
+            config: ConfigZone {
+                serial_number: placeholder_serial_number(),
+                revision: [0; 4],
+                aes_enable: 0,
+                i2c_enable: 1,
+                i2c_address: 0x60 << 1,
+                count_match: 0,
+                chip_mode: 0,
+                slot_configs: std::array::from_fn(|_| SlotConfig::default()),
+                // the encoding of an unused counter
+                counters: [[0xFF, 0xFF, 0xFF, 0xFF, 0, 0, 0, 0]; 2],
+                use_lock: 0,
+                volatile_key_permission: 0,
+                secure_boot: 0,
+                kdf_iv_loc: 0,
+                kdf_iv_str: 0,
+                user_extra: 0,
+                user_extra_add: 0,
+                lock_value: 0x55,
+                lock_config: 0x55,
+                slot_locked: 0xFFFF,
+                chip_options: 0,
+                x509_format: [0; 4],
+                key_configs: std::array::from_fn(|_| KeyConfig::default()),
+            },
+            invalid_slot: None,
+            invalid_address: false,
+        }
+    }
+}
+
+/// A well formed serial number for configs that aren't read from a device.
+fn placeholder_serial_number() -> SerialNumber {
+    SerialNumber([0x01, 0x23, 0, 0, 0, 0, 0, 0, 0xEE])
+}
+
+impl ConfigZoneBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn slot_config(mut self, slot: u8, config: SlotConfig) -> Self {
+        match self.config.slot_configs.get_mut(slot as usize) {
+            Some(entry) => *entry = config,
+            None => self.invalid_slot = Some(slot),
+        }
+        self
+    }
+
+    pub fn key_config(mut self, slot: u8, config: KeyConfig) -> Self {
+        match self.config.key_configs.get_mut(slot as usize) {
+            Some(entry) => *entry = config,
+            None => self.invalid_slot = Some(slot),
+        }
+        self
+    }
+
+    /// Sets the 7 bit I2C address the device answers on after the config is
+    /// locked.
+    pub fn i2c_address(mut self, address: u16) -> Self {
+        self.invalid_address = address > 0x7F;
+        self.config.i2c_address = (address << 1) as u8;
+        self
+    }
+
+    pub fn chip_mode(mut self, chip_mode: u8) -> Self {
+        self.config.chip_mode = chip_mode;
+        self
+    }
+
+    /// Returns the config, or an error for out of range slots and addresses
+    /// and for slots whose slot and key configs contradict each other.
+    pub fn build(self) -> Result<ConfigZone> {
+        if let Some(slot) = self.invalid_slot {
+            return Err(Error::invalid_slot(slot));
+        }
+        if self.invalid_address {
+            return Err(Error::invalid_address());
+        }
+        for slot in 0..=MAX_SLOT {
+            let slot_config = &self.config.slot_configs[slot as usize];
+            let key_config = &self.config.key_configs[slot as usize];
+            key_config.validate(slot)?;
+            let is_ecc = key_config.key_type() == KeyConfigType::Ecc;
+            if slot_config.secret() && is_ecc && !key_config.private() {
+                return Err(Error::invalid_slot_config(
+                    slot,
+                    "secret ECC slot is configured for a public key",
+                ));
+            }
+        }
+        Ok(self.config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let mut raw = [0u8; CONFIG_ZONE_SIZE];
+        raw[0..4].copy_from_slice(&[0x01, 0x23, 0x11, 0x22]);
+        raw[4..8].copy_from_slice(&[0x00, 0x00, 0x60, 0x03]);
+        raw[8..13].copy_from_slice(&[0x33, 0x44, 0x55, 0x66, 0xEE]);
+        raw[16] = 0xC0;
+        raw[22..24].copy_from_slice(&[0x83, 0x20]); // slot 1
+        raw[86] = 0x55;
+        raw[88..90].copy_from_slice(&[0xFE, 0xFF]);
+        raw[98..100].copy_from_slice(&[0x33, 0x00]); // slot 1
+
+        let config = ConfigZone::try_from(&raw[..]).expect("config zone");
+        assert_eq!(
+            [0x01, 0x23, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0xEE],
+            config.serial_number.0
+        );
+        assert_eq!(0xC0, config.i2c_address);
+        assert_eq!(0x8320, u16::from(&config.slot_configs[1]));
+        assert_eq!(0x3300, u16::from(&config.key_configs[1]));
+        assert_eq!(0x55, config.lock_value);
+        assert_eq!(0xFFFE, config.slot_locked);
+        assert_eq!(raw, config.to_bytes());
+    }
+
+    #[test]
+    fn builder() {
+        let config = ConfigZoneBuilder::new()
+            .i2c_address(0x35)
+            .build()
+            .expect("config zone");
+        assert_eq!(0x6A, config.i2c_address);
+
+        let mut public = KeyConfig::default();
+        public.set_private(false);
+        assert!(ConfigZoneBuilder::new()
+            .key_config(3, public)
+            .build()
+            .is_err());
+        assert!(ConfigZoneBuilder::new()
+            .slot_config(16, SlotConfig::default())
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    fn diff() {
+        let expected = ConfigZoneBuilder::new().build().expect("config zone");
+        let mut actual = ConfigZoneBuilder::new().build().expect("config zone");
+        actual.serial_number.0[4] = 0x42;
+        assert!(expected.diff(&actual).is_empty());
+
+        actual.key_configs[2] = KeyConfig::from(0);
+        assert_eq!(
+            vec![ConfigDiff {
+                slot: Some(2),
+                field: "key_config",
+                expected: 0x3300,
+                actual: 0,
+            }],
+            expected.diff(&actual)
+        );
+    }
+
+    #[test]
+    fn validate() {
+        let mut config = ConfigZoneBuilder::new().build().expect("config zone");
+        assert_eq!(Ok(()), config.validate());
+
+        config.slot_configs[1].set_secret(false);
+        config.slot_configs[4].set_write_config(0);
+        config.aes_enable = 1;
+        let errors = config.validate().expect_err("invalid config");
+        assert_eq!(3, errors.len());
+        assert_eq!(None, errors[0].slot);
+        assert_eq!(Some(1), errors[1].slot);
+        assert_eq!(Some(4), errors[2].slot);
+    }
+
+    #[test]
+    fn report() {
+        let mut config = ConfigZoneBuilder::new().build().expect("config zone");
+        config.lock_config = 0x00;
+        config.slot_locked = 0xFFFE;
+        let report = config.to_report();
+        assert!(report.contains("config zone: locked\ndata zone: unlocked\n"));
+        assert!(report.contains("slot  0: locked\n"));
+        assert!(report.contains("slot  1: unlocked\n"));
+        assert!(report.contains("key_type=Ecc private pub_info lockable auth_key=0"));
+    }
+
+    #[test]
+    fn features() {
+        let mut config = ConfigZoneBuilder::new().build().expect("config zone");
+        config.aes_enable = 0x01;
+        config.chip_options = 0x6D07;
+        assert_eq!(
+            Features {
+                aes_enabled: true,
+                power_on_self_test: true,
+                io_protection_enabled: true,
+                kdf_aes_enabled: true,
+                ecdh_protection: 1,
+                kdf_protection: 3,
+                io_protection_key: 6,
+            },
+            config.features()
+        );
+    }
+
+    #[test]
+    fn slots() {
+        let mut config = ConfigZoneBuilder::new().build().expect("config zone");
+        config.slot_locked = 0xFFFE;
+        let slots = config.slots();
+        assert_eq!(16, slots.len());
+        assert!(slots[0].locked && !slots[1].locked);
+        assert!(slots[3].key_config.private());
+        assert_eq!(
+            vec![36, 416, 72],
+            [7, 8, 15]
+                .iter()
+                .map(|s| slots[*s].size)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn serde_fixture() {
+        let fixture = include_str!("../tests/fixtures/config_zone.json");
+        let config: ConfigZone = serde_json::from_str(fixture).expect("config zone");
+        // a data slot for a certificate next to the default private keys
+        let expected = ConfigZoneBuilder::new()
+            .i2c_address(0x35)
+            .slot_config(
+                8,
+                SlotConfig::from(0).with_write_config(crate::_WriteConfig::Always),
+            )
+            .key_config(
+                8,
+                KeyConfig::from(0)
+                    .with_key_type(KeyConfigType::NotEcc)
+                    .with_lockable(true),
+            )
+            .build()
+            .expect("config zone");
+        assert_eq!(expected, config);
+
+        let json = serde_json::to_string(&config).expect("serialize");
+        let parsed: ConfigZone = serde_json::from_str(&json).expect("deserialize");
+        assert_eq!(config, parsed);
+    }
+}
@@ -5,26 +5,51 @@ pub(crate) const WAKE_DELAY: Duration = Duration::from_micros(1500);
 pub(crate) const ATCA_CMD_SIZE_MAX: u8 = 4 * 36 + 7;
 
 pub(crate) const CMD_STATUS_BYTE_SUCCESS: u8 = 0x00;
+pub(crate) const CMD_STATUS_BYTE_MISCOMPARE: u8 = 0x01;
 pub(crate) const CMD_STATUS_BYTE_PARSE: u8 = 0x03;
 pub(crate) const CMD_STATUS_BYTE_ECC: u8 = 0x05;
 pub(crate) const CMD_STATUS_BYTE_SELF_TEST: u8 = 0x07;
 pub(crate) const CMD_STATUS_BYTE_EXEC: u8 = 0x0F;
 pub(crate) const CMD_STATUS_BYTE_WATCHDOG: u8 = 0xEE;
+pub(crate) const CMD_STATUS_BYTE_WAKE: u8 = 0x11;
 pub(crate) const CMD_STATUS_BYTE_COMM: u8 = 0xFF;
 
 pub(crate) const ATCA_RSP_SIZE_MIN: u8 = 4;
 pub(crate) const ATCA_RSP_SIZE_MAX: u8 = 75;
+/// The status frame the chip returns right after a wake, including the crc
+pub(crate) const ATCA_WAKE_RESPONSE: [u8; 4] =
+    [ATCA_RSP_SIZE_MIN, CMD_STATUS_BYTE_WAKE, 0x33, 0x43];
 
 pub(crate) const ATCA_SWI_TRANSMIT_FLAG: u8 = 0x88;
 pub(crate) const ATCA_SWI_SLEEP_FLAG: u8 = 0xCC;
+pub(crate) const ATCA_SWI_IDLE_FLAG: u8 = 0xBB;
 pub(crate) const ATCA_SWI_COMMAND_FLAG: u8 = 0x77;
 pub(crate) const ATCA_I2C_COMMAND_FLAG: u8 = 0x03;
 pub(crate) const ATCA_INFO: u8 = 0x30;
 pub(crate) const ATCA_READ: u8 = 0x02;
+pub(crate) const ATCA_MAC: u8 = 0x08;
 pub(crate) const ATCA_WRITE: u8 = 0x12;
+pub(crate) const ATCA_GENDIG: u8 = 0x15;
 pub(crate) const ATCA_NONCE: u8 = 0x16;
 pub(crate) const ATCA_LOCK: u8 = 0x17;
 pub(crate) const ATCA_RANDOM: u8 = 0x1B;
+pub(crate) const ATCA_DERIVE_KEY: u8 = 0x1C;
+pub(crate) const ATCA_UPDATE_EXTRA: u8 = 0x20;
+pub(crate) const ATCA_COUNTER: u8 = 0x24;
+pub(crate) const ATCA_CHECKMAC: u8 = 0x28;
 pub(crate) const ATCA_GENKEY: u8 = 0x40;
 pub(crate) const ATCA_SIGN: u8 = 0x41;
 pub(crate) const ATCA_ECDH: u8 = 0x43;
+pub(crate) const ATCA_PRIVWRITE: u8 = 0x46;
+pub(crate) const ATCA_VERIFY: u8 = 0x45;
+pub(crate) const ATCA_SHA: u8 = 0x47;
+pub(crate) const ATCA_AES: u8 = 0x51;
+pub(crate) const ATCA_KDF: u8 = 0x56;
+pub(crate) const ATCA_SELF_TEST: u8 = 0x77;
+pub(crate) const ATCA_SECUREBOOT: u8 = 0x80;
+
+pub(crate) const ATCA_SHA_BLOCK_SIZE: usize = 64;
+pub(crate) const ATCA_KEY_SIZE: usize = 32;
+pub(crate) const ATCA_AES_BLOCK_SIZE: usize = 16;
+pub(crate) const ATCA_NONCE_NUMIN_SIZE: usize = 20;
+pub(crate) const ATCA_KDF_MESSAGE_MAX: usize = 128;
@@ -0,0 +1,122 @@
+//! PKCS#10 certificate signing requests for keys that never leave the chip.
+
+use crate::{transport::Transport, Ecc, Error, Result, Signature};
+use sha2::{Digest, Sha256};
+use std::{convert::TryFrom, str::FromStr};
+use x509_cert::{
+    der::{
+        asn1::{Any, BitString, ObjectIdentifier},
+        pem::LineEnding,
+        Encode, EncodePem,
+    },
+    name::Name,
+    request::{CertReq, CertReqInfo, Version},
+    spki::{AlgorithmIdentifierOwned, SubjectPublicKeyInfoOwned},
+};
+
+/// id-ecPublicKey from RFC 5480
+const ID_EC_PUBLIC_KEY: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.10045.2.1");
+/// The named curve secp256r1 from RFC 5480
+const SECP256R1: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.10045.3.1.7");
+/// ecdsa-with-SHA256 from RFC 5758
+const ECDSA_WITH_SHA256: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.10045.4.3.2");
+
+impl<T: Transport> Ecc<T> {
+    /// Builds a DER encoded PKCS#10 certificate signing request for the
+    /// private key in the given slot. The subject is an RFC 4514 string like
+    /// `CN=device,O=Example`. The public key is derived from the private key
+    /// with GenKey and the request is signed by the chip.
+    pub fn generate_csr(&mut self, key_slot: u8, subject: &str) -> Result<Vec<u8>> {
+        self.build_csr(key_slot, subject)?
+            .to_der()
+            .map_err(Error::x509)
+    }
+
+    /// Like [`generate_csr`](Self::generate_csr) but returns the request PEM
+    /// encoded.
+    pub fn generate_csr_pem(&mut self, key_slot: u8, subject: &str) -> Result<String> {
+        self.build_csr(key_slot, subject)?
+            .to_pem(LineEnding::LF)
+            .map_err(Error::x509)
+    }
+
+    fn build_csr(&mut self, key_slot: u8, subject: &str) -> Result<CertReq> {
+        let subject = Name::from_str(subject).map_err(Error::x509)?;
+        let public_key = self.genkey_public(key_slot)?;
+        let info = CertReqInfo {
+            version: Version::V1,
+            subject,
+            public_key: SubjectPublicKeyInfoOwned {
+                algorithm: AlgorithmIdentifierOwned {
+                    oid: ID_EC_PUBLIC_KEY,
+                    parameters: Some(Any::from(&SECP256R1)),
+                },
+                subject_public_key: BitString::from_bytes(&public_key.to_sec1_uncompressed())
+                    .map_err(Error::x509)?,
+            },
+            attributes: Default::default(),
+        };
+        let digest = Sha256::digest(info.to_der().map_err(Error::x509)?);
+        let signature = Signature::try_from(self.sign_digest(key_slot, &digest)?)?;
+        Ok(CertReq {
+            info,
+            algorithm: AlgorithmIdentifierOwned {
+                oid: ECDSA_WITH_SHA256,
+                parameters: None,
+            },
+            signature: BitString::from_bytes(&signature.to_der()).map_err(Error::x509)?,
+        })
+    }
+}
+
+#[cfg(all(test, feature = "p256"))]
+mod tests {
+    use super::*;
+    use crate::transport::MockTransport;
+    use p256::ecdsa::{
+        signature::{Signer, Verifier},
+        DerSignature, SigningKey,
+    };
+    use x509_cert::der::Decode;
+
+    /// The responses to GenKey, Random, Nonce and Sign
+    fn mock_ecc(public_key: &[u8], signature: &[u8]) -> Ecc<MockTransport> {
+        Ecc::new(MockTransport {
+            responses: vec![
+                MockTransport::frame(public_key),
+                MockTransport::frame(&[0; 32]),
+                MockTransport::frame(&[0]),
+                MockTransport::frame(signature),
+            ]
+            .into(),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn generate_csr() {
+        let signing_key = SigningKey::from_slice(&[0x01; 32]).expect("signing key");
+        let point = signing_key.verifying_key().to_encoded_point(false);
+        let public_key = &point.as_bytes()[1..];
+
+        // the request info doesn't depend on the signature, sign it the way
+        // the chip would and build the request again with that signature
+        let unsigned = mock_ecc(public_key, &[0x01; 64])
+            .generate_csr(0, "CN=device,O=Example")
+            .expect("csr");
+        let info = CertReq::from_der(&unsigned).expect("csr").info;
+        let signature: p256::ecdsa::Signature = signing_key.sign(&info.to_der().expect("info"));
+        let der = mock_ecc(public_key, &signature.to_bytes())
+            .generate_csr(0, "CN=device,O=Example")
+            .expect("csr");
+
+        let csr = CertReq::from_der(&der).expect("csr");
+        assert_eq!("CN=device,O=Example", csr.info.subject.to_string());
+        assert_eq!(ECDSA_WITH_SHA256, csr.algorithm.oid);
+        let signature = DerSignature::from_bytes(csr.signature.raw_bytes()).expect("signature");
+        assert!(signing_key
+            .verifying_key()
+            .verify(&csr.info.to_der().expect("info"), &signature)
+            .is_ok());
+    }
+}
@@ -1,33 +1,425 @@
-use crate::constants::ATCA_CMD_SIZE_MAX;
-use crate::transport::TransportProtocol;
+use crate::command::{AesMode, InfoMode, KdfAlgorithm, SecureBootMode, ShaMode};
+use crate::config_zone::CONFIG_ZONE_SIZE;
+use crate::constants::{
+    ATCA_AES_BLOCK_SIZE, ATCA_CMD_SIZE_MAX, ATCA_KDF_MESSAGE_MAX, ATCA_KEY_SIZE,
+    ATCA_NONCE_NUMIN_SIZE, ATCA_SHA_BLOCK_SIZE, CMD_STATUS_BYTE_SELF_TEST, WAKE_DELAY,
+};
+use crate::host;
+use crate::public_key::PUBLIC_KEY_SLOT_SIZE;
+use crate::secret::Secret;
+use crate::transport::{I2cTransport, Transport, TransportProtocol};
 use crate::{
-    command::{EccCommand, EccResponse},
-    Address, DataBuffer, Error, KeyConfig, Result, SlotConfig, Zone,
+    command::{EccCommand, EccError, EccResponse},
+    Address, ChipState, ConfigZone, DataBuffer, DeriveKeyConfig, DeviceType, Error, Features,
+    KeyConfig, KeyConfigType, PublicKey, Result, SerialNumber, SlotConfig, SlotInfo, WriteCommand,
+    WriteConfig, Zone,
 };
-use bytes::{BufMut, Bytes, BytesMut};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+#[cfg(feature = "p256")]
+use p256::ecdsa::signature::hazmat::PrehashVerifier;
 use sha2::{Digest, Sha256};
+use std::{
+    convert::TryFrom,
+    ops::{Deref, DerefMut},
+    thread,
+    time::{Duration, Instant},
+};
+
+pub use crate::command::{
+    DeriveKeyMode, KdfSource, KdfTarget, KeyType, MacMode, SelfTestFlags, SelfTestResult,
+    UpdateExtraTarget,
+};
+
+pub struct Ecc<T = TransportProtocol> {
+    transport: T,
+    sha_buffer: BytesMut,
+    retries: u8,
+    backoff_base: Duration,
+    backoff_cap: Duration,
+    wake_delay: Duration,
+    /// Set while a [`Session`] keeps the chip awake between commands
+    in_session: bool,
+    /// Set while a command runs with a timeout override
+    deadline: Option<Instant>,
+}
+
+/// Keeps the chip awake between commands so that TempKey and the other
+/// volatile state survive a sequence of commands. Derefs to the [`Ecc`] it
+/// was created from and puts the chip to sleep once when dropped.
+///
+/// The chip still falls asleep on its own when its watchdog expires, about
+/// 1.3s after the wake, so keep sessions short.
+pub struct Session<'a, T: Transport = TransportProtocol> {
+    ecc: &'a mut Ecc<T>,
+}
+
+impl<T: Transport> Deref for Session<'_, T> {
+    type Target = Ecc<T>;
+
+    fn deref(&self) -> &Ecc<T> {
+        self.ecc
+    }
+}
+
+impl<T: Transport> DerefMut for Session<'_, T> {
+    fn deref_mut(&mut self) -> &mut Ecc<T> {
+        self.ecc
+    }
+}
+
+impl<T: Transport> Drop for Session<'_, T> {
+    fn drop(&mut self) {
+        self.ecc.in_session = false;
+        self.ecc.transport.send_sleep();
+    }
+}
 
-pub use crate::command::KeyType;
+/// Round trip times of a command, see [`Ecc::measure_latency`]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct LatencyStats {
+    pub iterations: u32,
+    pub min: Duration,
+    pub max: Duration,
+    pub mean: Duration,
+    /// The time 99% of the round trips completed within
+    pub p99: Duration,
+}
 
-pub struct Ecc {
-    transport: TransportProtocol,
+impl LatencyStats {
+    fn from_samples(samples: &mut [Duration]) -> Self {
+        samples.sort();
+        let iterations = samples.len() as u32;
+        let p99 = (samples.len() * 99).div_ceil(100).max(1) - 1;
+        Self {
+            iterations,
+            min: samples[0],
+            max: samples[samples.len() - 1],
+            mean: samples.iter().sum::<Duration>() / iterations,
+            p99: samples[p99],
+        }
+    }
 }
 
 pub const MAX_SLOT: u8 = 15;
+pub const MAX_COUNTER: u8 = 1;
+/// The largest value a monotonic counter counts up to
+pub const MAX_COUNTER_VALUE: u32 = 2_097_151;
 
 pub(crate) const CMD_RETRIES: u8 = 10;
+const OTP_ZONE_SIZE: usize = 64;
+/// Slots 0 to 7 of 36 bytes, slot 8 of 416 bytes and slots 9 to 15 of 72.
+const DATA_ZONE_SIZE: usize = 8 * 36 + 416 + 7 * 72;
+/// Factory default 7 bit addresses of the generic ECC608 and the common
+/// pre-provisioned variants.
+const SCAN_ADDRESSES: &[u16] = &[0x60, 0x36, 0x35, 0x58];
+/// How long a sequence of commands may keep the chip awake before it has to be
+/// put to sleep, leaving a margin to the 1.3s watchdog timeout.
+const WATCHDOG_BUDGET: Duration = Duration::from_millis(1_000);
+
+/// Opens an [`Ecc`] with the retry and timing settings applied at
+/// construction. The device path and address are required, see
+/// [`Ecc::from_path`] for how the path picks the transport.
+#[derive(Default)]
+pub struct EccBuilder {
+    path: Option<String>,
+    address: Option<u16>,
+    retries: Option<u8>,
+    backoff: Option<(Duration, Duration)>,
+    wake_delay: Option<Duration>,
+}
+
+impl EccBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn path(mut self, path: &str) -> Self {
+        self.path = Some(path.to_string());
+        self
+    }
+
+    pub fn address(mut self, address: u16) -> Self {
+        self.address = Some(address);
+        self
+    }
+
+    /// See [`Ecc::with_retries`].
+    pub fn retries(mut self, retries: u8) -> Self {
+        self.retries = Some(retries);
+        self
+    }
+
+    /// See [`Ecc::with_backoff`].
+    pub fn backoff(mut self, base: Duration, cap: Duration) -> Self {
+        self.backoff = Some((base, cap));
+        self
+    }
+
+    /// See [`Ecc::with_wake_delay`].
+    pub fn wake_delay(mut self, wake_delay: Duration) -> Self {
+        self.wake_delay = Some(wake_delay);
+        self
+    }
+
+    /// Opens the device, failing with [`Error::InvalidAddress`] if the path
+    /// or the address is missing.
+    pub fn build(self) -> Result<Ecc> {
+        let (path, address) = match (self.path, self.address) {
+            (Some(path), Some(address)) => (path, address),
+            _ => return Err(Error::invalid_address()),
+        };
+        let mut ecc = Ecc::from_path(&path, address)?;
+        if let Some(retries) = self.retries {
+            ecc = ecc.with_retries(retries);
+        }
+        if let Some((base, cap)) = self.backoff {
+            ecc = ecc.with_backoff(base, cap);
+        }
+        if let Some(wake_delay) = self.wake_delay {
+            ecc = ecc.with_wake_delay(wake_delay);
+        }
+        Ok(ecc)
+    }
+}
 
 impl Ecc {
+    pub fn builder() -> EccBuilder {
+        EccBuilder::new()
+    }
+
     pub fn from_path(path: &str, address: u16) -> Result<Self> {
         let transport = TransportProtocol::from_path(path, address)?;
+        Ok(Self::new(transport))
+    }
+
+    /// Opens an ECC connected in single wire mode through the UART at the
+    /// given path, regardless of the path name. [`Ecc::from_path`] only picks
+    /// the single wire transport for `/dev/tty*` paths.
+    pub fn from_swi(path: &str) -> Result<Self> {
+        let transport = TransportProtocol::from_swi(path)?;
+        Ok(Self::new(transport))
+    }
+
+    /// Probes the common ECC608 addresses on the given I2C bus and returns the
+    /// ones that answered a wake. Addresses without a device are skipped.
+    pub fn scan(path: &str) -> Result<Vec<u16>> {
+        let mut found = vec![];
+        for address in SCAN_ADDRESSES {
+            if I2cTransport::probe(path, *address)? {
+                found.push(*address);
+            }
+        }
+        Ok(found)
+    }
+
+    /// Verifies an ECDSA P256 signature (`r || s`) over the SHA256 digest of
+    /// the given message on the host, without using the chip. This is useful
+    /// to check signatures produced by [`Ecc::sign`].
+    ///
+    /// Returns `Ok(false)` for a signature that doesn't verify, and an error
+    /// for a public key that isn't on the curve or a signature that isn't a
+    /// 64 byte `r || s` with both scalars in range.
+    #[cfg(feature = "p256")]
+    pub fn verify_local(public_key: &PublicKey, message: &[u8], signature: &[u8]) -> Result<bool> {
+        let signature = crate::Signature::try_from(signature)?.to_p256()?;
+        let key = public_key.to_p256()?;
+        let digest = Sha256::digest(message);
+        Ok(key.verify_prehash(&digest, &signature).is_ok())
+    }
+}
+
+impl<T: Transport> Ecc<T> {
+    /// Creates an ECC handle on top of the given transport, for example a
+    /// custom [`Transport`] implementation for a bus this crate doesn't
+    /// support. [`Ecc::from_path`] opens the Linux I2C and single wire
+    /// transports.
+    pub fn new(transport: T) -> Self {
+        Self {
+            transport,
+            sha_buffer: BytesMut::with_capacity(ATCA_SHA_BLOCK_SIZE),
+            retries: CMD_RETRIES,
+            backoff_base: Duration::ZERO,
+            backoff_cap: Duration::ZERO,
+            wake_delay: WAKE_DELAY,
+            in_session: false,
+            deadline: None,
+        }
+    }
+
+    /// The transport the chip is talked to over
+    pub fn transport(&self) -> &T {
+        &self.transport
+    }
+
+    pub fn transport_mut(&mut self) -> &mut T {
+        &mut self.transport
+    }
+
+    /// Sets how many times commands are attempted before giving up. Defaults
+    /// to 10. Sequences that depend on chip state between commands are always
+    /// attempted once.
+    pub fn with_retries(mut self, retries: u8) -> Self {
+        self.retries = retries.max(1);
+        self
+    }
+
+    /// Waits between command retries, starting at `base` and doubling on
+    /// every retry up to `cap`. By default retries are sent without delay.
+    pub fn with_backoff(mut self, base: Duration, cap: Duration) -> Self {
+        self.backoff_base = base;
+        self.backoff_cap = cap;
+        self
+    }
 
-        Ok(Self { transport })
+    /// Sets how long to wait after the wake pulse before talking to the chip.
+    /// Defaults to the typical 1.5ms wake high delay of the datasheet, slow
+    /// boards may need more.
+    pub fn with_wake_delay(mut self, wake_delay: Duration) -> Self {
+        self.wake_delay = wake_delay;
+        self
+    }
+
+    /// The delay before the given retry, where retry 1 is the second attempt
+    fn backoff(&self, retry: u8) -> Duration {
+        let factor = 1u32 << (retry - 1).min(16);
+        self.backoff_base
+            .saturating_mul(factor)
+            .min(self.backoff_cap)
+    }
+
+    /// Starts a session that doesn't put the chip to sleep after each command.
+    /// Commands outside a session sleep when they complete, which wipes
+    /// TempKey.
+    pub fn session(&mut self) -> Session<'_, T> {
+        self.in_session = true;
+        Session { ecc: self }
+    }
+
+    /// Points this handle at a new I2C address, for example after the
+    /// address in the config zone was changed. This does not write the config
+    /// zone. The chip only picks up a new configured address after it has
+    /// gone to sleep and been woken again, which the next command does.
+    ///
+    /// Returns an error for single wire transports, which have no address.
+    pub fn set_device_address(&mut self, new_address: u16) -> Result {
+        self.transport.set_address(new_address)
+    }
+
+    /// Wakes the chip. Every command wakes the chip by itself, this is only
+    /// needed to control the power state explicitly, for example to wake the
+    /// chip ahead of a burst of commands.
+    pub fn wake(&mut self) -> Result {
+        self.transport.send_wake(self.wake_delay)
+    }
+
+    /// Puts the chip to sleep, which clears TempKey and the other volatile
+    /// state. Commands put the chip to sleep by themselves when they complete
+    /// unless they run in a [`Session`].
+    pub fn sleep(&mut self) -> Result {
+        self.transport.send_sleep();
+        Ok(())
+    }
+
+    /// Puts the chip in idle mode. Unlike sleep, which every command enters
+    /// by default when it completes, idle keeps TempKey and the other volatile
+    /// state so a sequence of commands can continue after the next wake. The
+    /// chip still draws more power than when asleep.
+    pub fn idle(&mut self) -> Result {
+        self.transport.send_idle()
     }
 
     pub fn get_info(&mut self) -> Result<Bytes> {
         self.send_command(&EccCommand::info())
     }
 
+    /// Returns the 4 byte device revision.
+    pub fn info_revision(&mut self) -> Result<[u8; 4]> {
+        let bytes = self.info(InfoMode::Revision, 0)?;
+        check_length(&bytes, 4)?;
+        let mut result = [0u8; 4];
+        result.copy_from_slice(&bytes);
+        Ok(result)
+    }
+
+    /// Returns the part number of the chip, decoded from
+    /// [`info_revision`](Self::info_revision).
+    pub fn device_type(&mut self) -> Result<DeviceType> {
+        self.info_revision().map(DeviceType::from)
+    }
+
+    /// Times the given number of Info commands, at least one, each a full
+    /// wake, command and sleep round trip through the usual command path
+    /// with its retries. In a [`Session`] the wake and sleep are left out,
+    /// which shows what keeping the chip awake saves.
+    pub fn measure_latency(&mut self, iterations: u32) -> Result<LatencyStats> {
+        let mut samples = Vec::with_capacity(iterations.max(1) as usize);
+        for _ in 0..iterations.max(1) {
+            let start = Instant::now();
+            self.info_revision()?;
+            samples.push(start.elapsed());
+        }
+        Ok(LatencyStats::from_samples(&mut samples))
+    }
+
+    /// Returns the volatile state of the chip, including the validity and
+    /// origin of TempKey.
+    pub fn info_state(&mut self) -> Result<ChipState> {
+        self.info(InfoMode::State, 0)
+            .map(|bytes| ChipState::from(&bytes[..]))
+    }
+
+    /// Returns whether the ECC private key in the given slot is valid. Only
+    /// meaningful for slots with KeyConfig.Lockable set.
+    pub fn info_key_valid(&mut self, slot: u8) -> Result<bool> {
+        check_slot(slot)?;
+        self.info(InfoMode::KeyValid, slot)
+            .map(|bytes| bytes[0] == 0x01)
+    }
+
+    /// Returns the current state of the GPIO pin.
+    ///
+    /// The GPIO pin has to be configured as an output in ChipMode, otherwise the
+    /// chip rejects the command and its error is returned.
+    pub fn gpio_get(&mut self) -> Result<bool> {
+        self.info(InfoMode::Gpio, 0x00)
+            .map(|bytes| bytes[0] & 0x01 == 0x01)
+    }
+
+    /// Drives the GPIO pin high or low and returns the resulting pin state.
+    /// See [`Ecc::gpio_get`] for configuration requirements.
+    pub fn gpio_set(&mut self, high: bool) -> Result<bool> {
+        // bit 1 requests a set, bit 0 is the new state
+        self.info(InfoMode::Gpio, 0x02 | high as u8)
+            .map(|bytes| bytes[0] & 0x01 == 0x01)
+    }
+
+    /// Returns whether the persistent latch is set. Slots with
+    /// KeyConfig.PersistentDisable set can only be used while it is.
+    pub fn persistent_latch(&mut self) -> Result<bool> {
+        self.info(InfoMode::VolKeyPermit, 0x00)
+            .map(|bytes| bytes[0] & 0x01 == 0x01)
+    }
+
+    /// Sets or clears the persistent latch, which keeps its state across
+    /// sleep and power cycles.
+    ///
+    /// Setting the latch requires VolatileKeyPermission to be enabled in the
+    /// config and a prior authorization with the key in its slot, like a
+    /// successful [`check_mac`](Self::check_mac) in the same
+    /// [`Session`]. Without it the chip rejects the command and its error is
+    /// returned. Clearing the latch needs no authorization.
+    pub fn set_persistent_latch(&mut self, state: bool) -> Result {
+        // bit 1 requests a set, bit 0 is the new state
+        self.info(InfoMode::VolKeyPermit, 0x02 | state as u8)
+            .map(|_| ())
+    }
+
+    fn info(&mut self, mode: InfoMode, param: u8) -> Result<Bytes> {
+        let bytes = self.send_command(&EccCommand::info_mode(mode, param))?;
+        check_length(&bytes, 4)?;
+        Ok(bytes)
+    }
+
     /// Returns the 9 bytes that represent the serial number of the ECC. Per
     /// section 2.2.6 of the Data Sheet the first two, and last byte of the
     /// returned binary will always be `[0x01, 0x23]` and `0xEE`
@@ -39,12 +431,59 @@ impl Ecc {
         Ok(result.freeze())
     }
 
+    /// Returns the serial number of the ECC. Fails if the fixed bytes of the
+    /// serial number don't read back as expected.
+    pub fn serial_number(&mut self) -> Result<SerialNumber> {
+        SerialNumber::try_from(&self.get_serial()?[..])
+    }
+
     pub fn genkey(&mut self, key_type: KeyType, slot: u8) -> Result<Bytes> {
+        check_slot(slot)?;
         self.send_command(&EccCommand::genkey(key_type, slot))
     }
 
+    /// Returns the public key for the private key stored in the given slot.
+    pub fn genkey_public(&mut self, slot: u8) -> Result<PublicKey> {
+        check_slot(slot)?;
+        self.genkey_point(KeyType::Public, slot)
+    }
+
+    /// Generates a new private key in the given slot and returns its public
+    /// key.
+    pub fn genkey_private(&mut self, slot: u8) -> Result<PublicKey> {
+        check_slot(slot)?;
+        self.genkey_point(KeyType::Private, slot)
+    }
+
+    /// Runs GenKey in public key digest mode, which combines the public key
+    /// for the given slot, the 3 bytes of `other_data` and the current TempKey
+    /// into a new TempKey. No key is generated or returned.
+    ///
+    /// TempKey has to be set up with a Nonce first, and the chip is left awake
+    /// so the digest can be used by the next command.
+    pub fn genkey_digest(&mut self, slot: u8, other_data: &[u8; 3]) -> Result {
+        check_slot(slot)?;
+        self.send_command_retries(&EccCommand::genkey_digest(slot, other_data), false, 1)
+            .map(|_| ())
+    }
+
+    fn genkey_point(&mut self, key_type: KeyType, slot: u8) -> Result<PublicKey> {
+        check_slot(slot)?;
+        let bytes = self.genkey(key_type, slot)?;
+        PublicKey::try_from(&bytes[..])
+    }
+
     pub fn get_slot_config(&mut self, slot: u8) -> Result<SlotConfig> {
-        let bytes = self.read(false, &Address::slot_config(slot)?)?;
+        check_slot(slot)?;
+        self.read_slot_config(slot, true)
+    }
+
+    fn read_slot_config(&mut self, slot: u8, sleep: bool) -> Result<SlotConfig> {
+        let bytes = self.send_command_retries(
+            &EccCommand::read(false, Address::slot_config(slot)?),
+            sleep,
+            self.retries,
+        )?;
         let (s0, s1) = bytes.split_at(2);
         match slot & 1 == 0 {
             true => Ok(SlotConfig::from(s0)),
@@ -53,6 +492,7 @@ impl Ecc {
     }
 
     pub fn set_slot_config(&mut self, slot: u8, config: &SlotConfig) -> Result {
+        check_slot(slot)?;
         let slot_address = Address::slot_config(slot)?;
         let bytes = self.read(false, &slot_address)?;
         let (s0, s1) = bytes.split_at(2);
@@ -70,7 +510,55 @@ impl Ecc {
         self.write(&slot_address, &new_bytes.freeze())
     }
 
+    /// Reads and parses the whole config zone.
+    pub fn read_config(&mut self) -> Result<ConfigZone> {
+        let mut bytes = BytesMut::with_capacity(CONFIG_ZONE_SIZE);
+        for block in 0..4 {
+            bytes.extend_from_slice(&self.read(true, &Address::config(block, 0)?)?);
+        }
+        ConfigZone::try_from(&bytes[..])
+    }
+
+    /// Reads the config zone and returns the optional features enabled on
+    /// the chip, see [`ConfigZone::features`].
+    ///
+    /// There is no flag for the KDF command as a whole: every ECC608 runs
+    /// its HKDF and PRF modes, ChipOptions only gates the AES mode, which is
+    /// reported as [`Features::kdf_aes_enabled`].
+    pub fn features(&mut self) -> Result<Features> {
+        Ok(self.read_config()?.features())
+    }
+
+    /// Reads the config zone once and returns the configuration and lock
+    /// state of every slot, see [`ConfigZone::slots`].
+    pub fn slots(&mut self) -> Result<Vec<SlotInfo>> {
+        Ok(self.read_config()?.slots())
+    }
+
+    /// Writes the writable part of the config zone from the given config,
+    /// in address order. The serial number, revision and other bytes below
+    /// the I2C address are read only, and the user extra and lock bytes are
+    /// only changed by UpdateExtra and Lock, so those are skipped. Only
+    /// works while the config zone is unlocked.
+    pub fn write_config(&mut self, config: &ConfigZone) -> Result {
+        let bytes = config.to_bytes();
+        for word in 4..8 {
+            self.write_config_word(0, word, &bytes)?;
+        }
+        self.write(&Address::config(1, 0)?, &bytes[32..64])?;
+        for word in (0..8).filter(|word| *word != 5) {
+            self.write_config_word(2, word, &bytes)?;
+        }
+        self.write(&Address::config(3, 0)?, &bytes[96..128])
+    }
+
+    fn write_config_word(&mut self, block: u8, word: u8, bytes: &[u8]) -> Result {
+        let offset = block as usize * 32 + word as usize * 4;
+        self.write(&Address::config(block, word)?, &bytes[offset..offset + 4])
+    }
+
     pub fn get_key_config(&mut self, slot: u8) -> Result<KeyConfig> {
+        check_slot(slot)?;
         let bytes = self.read(false, &Address::key_config(slot)?)?;
         let (s0, s1) = bytes.split_at(2);
         match slot & 1 == 0 {
@@ -80,6 +568,7 @@ impl Ecc {
     }
 
     pub fn set_key_config(&mut self, slot: u8, config: &KeyConfig) -> Result {
+        check_slot(slot)?;
         let slot_address = Address::key_config(slot)?;
         let bytes = self.read(false, &slot_address)?;
         let (s0, s1) = bytes.split_at(2);
@@ -106,26 +595,163 @@ impl Ecc {
         }
     }
 
+    pub fn is_config_locked(&mut self) -> Result<bool> {
+        self.get_locked(&Zone::Config)
+    }
+
+    pub fn is_data_locked(&mut self) -> Result<bool> {
+        self.get_locked(&Zone::Data)
+    }
+
+    /// Returns whether the given slot has been individually locked, as
+    /// recorded by a cleared bit in the SlotLocked bytes of the config zone.
+    pub fn is_slot_locked(&mut self, slot: u8) -> Result<bool> {
+        check_slot(slot)?;
+        let bytes = self.read(false, &Address::config(2, 6)?)?;
+        let slot_locked = u16::from_le_bytes([bytes[0], bytes[1]]);
+        Ok(slot_locked & (1 << slot) == 0)
+    }
+
     pub fn set_locked(&mut self, zone: Zone) -> Result {
         self.send_command(&EccCommand::lock(zone)).map(|_| ())
     }
 
+    /// Locks the data and OTP zones only if their contents match the given
+    /// image, which protects against locking a device whose data zone was
+    /// corrupted during provisioning. The image is every slot in order,
+    /// 0 to 15 at their full sizes, followed by the 64 byte OTP zone, as it
+    /// was written. The chip checks the CRC of the image against its actual
+    /// contents and refuses to lock on a mismatch, which is returned as
+    /// [`Error::Ecc`]. Locking is permanent.
+    pub fn set_data_locked_crc(&mut self, image: &[u8]) -> Result {
+        if image.len() != DATA_ZONE_SIZE + OTP_ZONE_SIZE {
+            return Err(Error::invalid_length(
+                DATA_ZONE_SIZE + OTP_ZONE_SIZE,
+                image.len(),
+            ));
+        }
+        let summary = crate::command::crc(image);
+        self.send_command(&EccCommand::lock_crc(Zone::Data, summary))
+            .map(|_| ())
+    }
+
+    /// Locks a single data slot so its contents can no longer be changed.
+    /// The chip refuses this unless the slot's KeyConfig.Lockable bit is set,
+    /// check the result with [`is_slot_locked`](Self::is_slot_locked).
+    /// Locking is permanent.
+    pub fn lock_slot(&mut self, slot: u8) -> Result {
+        check_slot(slot)?;
+        self.send_command(&EccCommand::lock_slot(slot)).map(|_| ())
+    }
+
+    /// Reads back and validates the config zone and only locks it if
+    /// [`ConfigZone::validate`] finds no problems, which are returned in
+    /// [`Error::InvalidConfig`] otherwise. Locking is permanent.
+    pub fn set_config_locked_validated(&mut self) -> Result {
+        self.read_config()?
+            .validate()
+            .map_err(Error::invalid_config)?;
+        self.set_locked(Zone::Config)
+    }
+
     pub fn sign(&mut self, key_slot: u8, data: &[u8]) -> Result<Bytes> {
-        let _ = self.send_command_retries(&EccCommand::random(), false, 1)?;
+        check_slot(key_slot)?;
         let digest = Sha256::digest(data);
+        self.sign_buffer(DataBuffer::MessageDigest, key_slot, &digest)
+    }
+
+    /// Like [`Ecc::sign`] but returns the 64 byte `r || s` signature as an
+    /// array.
+    pub fn sign_array(&mut self, key_slot: u8, data: &[u8]) -> Result<[u8; 64]> {
+        let bytes = self.sign(key_slot, data)?;
+        check_length(&bytes, 64)?;
+        let mut signature = [0u8; 64];
+        signature.copy_from_slice(&bytes);
+        Ok(signature)
+    }
+
+    /// Signs a 32 byte digest computed by the caller with the private key in
+    /// the given slot. Unlike [`Ecc::sign`] the digest is used as is and not
+    /// hashed again.
+    pub fn sign_digest(&mut self, key_slot: u8, digest: &[u8]) -> Result<Bytes> {
+        check_slot(key_slot)?;
+        check_length(digest, 32)?;
+        self.sign_buffer(DataBuffer::TempKey, key_slot, digest)
+    }
+
+    /// Loads the digest into the given buffer and signs it. The chip must not
+    /// sleep until the sign completes or the buffer is lost.
+    fn sign_buffer(&mut self, buffer: DataBuffer, key_slot: u8, digest: &[u8]) -> Result<Bytes> {
+        let _ = self.send_command_retries(&EccCommand::random(), false, 1)?;
         let _ = self.send_command_retries(
-            &EccCommand::nonce(DataBuffer::MessageDigest, Bytes::copy_from_slice(&digest)),
+            &EccCommand::nonce(buffer, Bytes::copy_from_slice(digest)),
             false,
             1,
         )?;
-        self.send_command_retries(
-            &EccCommand::sign(DataBuffer::MessageDigest, key_slot),
-            true,
-            1,
-        )
+        self.send_command_retries(&EccCommand::sign(buffer, key_slot), true, 1)
+    }
+
+    /// Signs each of the given 32 byte digests with the private key in the
+    /// given slot, like [`Ecc::sign_digest`], and returns the signatures in
+    /// the same order.
+    ///
+    /// The chip is woken once and only put back to sleep at the end, or when
+    /// it would otherwise run into its watchdog timeout, which saves the wake
+    /// and sleep handshake for most of the digests.
+    pub fn sign_batch(&mut self, key_slot: u8, digests: &[[u8; 32]]) -> Result<Vec<Bytes>> {
+        check_slot(key_slot)?;
+        let result = self.sign_batch_awake(key_slot, digests, WATCHDOG_BUDGET);
+        if !self.in_session {
+            self.transport.send_sleep();
+        }
+        result
+    }
+
+    /// Signs the digests, putting the chip to sleep and waking it again
+    /// whenever the next signature would take it past `budget`. Random is
+    /// run after every wake, ahead of the first Nonce, as in [`Ecc::sign`].
+    fn sign_batch_awake(
+        &mut self,
+        key_slot: u8,
+        digests: &[[u8; 32]],
+        budget: Duration,
+    ) -> Result<Vec<Bytes>> {
+        let sign_duration = self.transport.command_duration(&EccCommand::random())
+            + self
+                .transport
+                .command_duration(&EccCommand::nonce(DataBuffer::TempKey, Bytes::new()))
+            + self
+                .transport
+                .command_duration(&EccCommand::sign(DataBuffer::TempKey, key_slot));
+        let mut signatures = Vec::with_capacity(digests.len());
+        self.transport.send_wake(self.wake_delay)?;
+        let mut awake = Instant::now();
+        let _ = self.send_command_inner(&EccCommand::random(), false, false, 1)?;
+        for (n, digest) in digests.iter().enumerate() {
+            if n > 0 && awake.elapsed() + sign_duration > budget {
+                self.transport.send_sleep();
+                self.transport.send_wake(self.wake_delay)?;
+                awake = Instant::now();
+                let _ = self.send_command_inner(&EccCommand::random(), false, false, 1)?;
+            }
+            let _ = self.send_command_inner(
+                &EccCommand::nonce(DataBuffer::TempKey, Bytes::copy_from_slice(digest)),
+                false,
+                false,
+                1,
+            )?;
+            signatures.push(self.send_command_inner(
+                &EccCommand::sign(DataBuffer::TempKey, key_slot),
+                false,
+                false,
+                1,
+            )?);
+        }
+        Ok(signatures)
     }
 
     pub fn ecdh(&mut self, key_slot: u8, x: &[u8], y: &[u8]) -> Result<Bytes> {
+        check_slot(key_slot)?;
         self.send_command(&EccCommand::ecdh(
             Bytes::copy_from_slice(x),
             Bytes::copy_from_slice(y),
@@ -133,15 +759,915 @@ impl Ecc {
         ))
     }
 
+    /// Runs ECDH with the private key in `key_slot` and the given peer public
+    /// key and returns the 32 byte shared secret.
+    pub fn ecdh_secret(&mut self, key_slot: u8, peer: &PublicKey) -> Result<[u8; 32]> {
+        let bytes = self.ecdh(key_slot, &peer.x, &peer.y)?;
+        check_length(&bytes, 32)?;
+        let mut secret = [0u8; 32];
+        secret.copy_from_slice(&bytes);
+        Ok(secret)
+    }
+
+    /// Runs ECDH with the private key in `key_slot` and the given peer public
+    /// key into TempKey, then HKDF from TempKey with `info` as the HKDF info,
+    /// and returns the 32 byte derived key. The shared secret never leaves
+    /// the chip.
+    ///
+    /// The chip is kept awake between the two commands since sleeping clears
+    /// TempKey.
+    pub fn ecdh_kdf(&mut self, key_slot: u8, peer: &PublicKey, info: &[u8]) -> Result<[u8; 32]> {
+        check_slot(key_slot)?;
+        if info.len() > ATCA_KDF_MESSAGE_MAX {
+            return Err(Error::invalid_length(ATCA_KDF_MESSAGE_MAX, info.len()));
+        }
+        self.send_command_retries(
+            &EccCommand::ecdh_to_tempkey(
+                Bytes::copy_from_slice(&peer.x),
+                Bytes::copy_from_slice(&peer.y),
+                key_slot,
+            ),
+            false,
+            1,
+        )?;
+        let bytes = self.send_command_retries(
+            &EccCommand::kdf(
+                KdfAlgorithm::Hkdf,
+                KdfSource::TempKey,
+                KdfTarget::Output,
+                info,
+            ),
+            true,
+            1,
+        )?;
+        check_length(&bytes, 32)?;
+        let mut key = [0u8; 32];
+        key.copy_from_slice(&bytes);
+        Ok(key)
+    }
+
+    /// Runs ECDH with the private key in `key_slot` and the given peer public
+    /// key, storing the shared secret in `output_slot` instead of returning it.
+    ///
+    /// The chip writes the secret to the slot following the (even) key slot
+    /// when the key slot's ReadKey has the ECDH write slot bit set, so
+    /// `output_slot` has to be `key_slot + 1`.
+    pub fn ecdh_to_slot(
+        &mut self,
+        key_slot: u8,
+        peer_x: &[u8],
+        peer_y: &[u8],
+        output_slot: u8,
+    ) -> Result {
+        check_slot(key_slot)?;
+        if output_slot > MAX_SLOT || key_slot & 1 != 0 || output_slot != key_slot + 1 {
+            return Err(Error::invalid_slot(output_slot));
+        }
+        check_length(peer_x, 32)?;
+        check_length(peer_y, 32)?;
+        let read_key = self.get_slot_config(key_slot)?.read_key();
+        if !read_key.ecdh_operation() {
+            return Err(Error::invalid_slot_config(key_slot, "ecdh not permitted"));
+        }
+        if !read_key.ecdh_write_slot() {
+            return Err(Error::invalid_slot_config(
+                key_slot,
+                "ecdh output to slot not enabled",
+            ));
+        }
+        self.ecdh(key_slot, peer_x, peer_y).map(|_| ())
+    }
+
+    /// Verifies an ECDSA P256 signature over the SHA256 digest of the given
+    /// message against the given external public key. Both the signature
+    /// (`r || s`) and the public key (`x || y`) are expected to be 64 bytes.
+    ///
+    /// Returns `Ok(false)` if the chip reports that the signature does not
+    /// match.
+    pub fn verify_extern(
+        &mut self,
+        message: &[u8],
+        signature: &[u8],
+        public_key: &[u8],
+    ) -> Result<bool> {
+        check_length(signature, 64)?;
+        check_length(public_key, 64)?;
+        let digest = Sha256::digest(message);
+        let _ = self.send_command_retries(
+            &EccCommand::nonce(DataBuffer::TempKey, Bytes::copy_from_slice(&digest)),
+            false,
+            1,
+        )?;
+        verify_result(self.send_command_retries(
+            &EccCommand::verify_extern(
+                DataBuffer::TempKey,
+                Bytes::copy_from_slice(signature),
+                Bytes::copy_from_slice(public_key),
+            ),
+            true,
+            1,
+        ))
+    }
+
+    /// Verifies an ECDSA P256 signature over the SHA256 digest of the given
+    /// message against the public key stored in the given slot. The slot
+    /// must be configured to hold an ECC public key.
+    ///
+    /// Returns the same results as [`Ecc::verify_extern`].
+    pub fn verify_stored(
+        &mut self,
+        key_slot: u8,
+        message: &[u8],
+        signature: &[u8],
+    ) -> Result<bool> {
+        check_slot(key_slot)?;
+        check_length(signature, 64)?;
+        let key_config = self.get_key_config(key_slot)?;
+        if key_config.private() || key_config.key_type() != KeyConfigType::Ecc {
+            return Err(Error::invalid_slot_config(
+                key_slot,
+                "not an ecc public key",
+            ));
+        }
+        let digest = Sha256::digest(message);
+        let _ = self.send_command_retries(
+            &EccCommand::nonce(DataBuffer::TempKey, Bytes::copy_from_slice(&digest)),
+            false,
+            1,
+        )?;
+        verify_result(self.send_command_retries(
+            &EccCommand::verify_stored(
+                DataBuffer::TempKey,
+                Bytes::copy_from_slice(signature),
+                key_slot,
+            ),
+            true,
+            1,
+        ))
+    }
+
+    /// Returns the current value of the given monotonic counter. The chip
+    /// has two counters with ids 0 and 1.
+    pub fn counter_read(&mut self, counter_id: u8) -> Result<u32> {
+        self.counter(counter_id, false)
+    }
+
+    /// Increments the given monotonic counter and returns the new value.
+    /// Once a counter reaches [`MAX_COUNTER_VALUE`] the chip refuses to
+    /// increment it further with an execution error, which is returned as
+    /// [`Error::CounterExhausted`].
+    pub fn counter_increment(&mut self, counter_id: u8) -> Result<u32> {
+        match self.counter(counter_id, true) {
+            Err(
+                err @ Error::Ecc {
+                    error: EccError::ExecError,
+                    ..
+                },
+            ) => match self.counter_read(counter_id) {
+                Ok(MAX_COUNTER_VALUE) => Err(Error::counter_exhausted(counter_id)),
+                _ => Err(err),
+            },
+            result => result,
+        }
+    }
+
+    fn counter(&mut self, counter_id: u8, increment: bool) -> Result<u32> {
+        if counter_id > MAX_COUNTER {
+            return Err(Error::invalid_counter(counter_id));
+        }
+        let mut bytes = self.send_command(&EccCommand::counter(counter_id, increment))?;
+        if bytes.len() != 4 {
+            return Err(Error::invalid_length(4, bytes.len()));
+        }
+        Ok(bytes.get_u32_le())
+    }
+
+    /// Starts a SHA256 digest computation using the chip's SHA engine. Use
+    /// [`Ecc::sha_update`] to feed data and [`Ecc::sha_end`] to get the
+    /// digest.
+    ///
+    /// The chip keeps the digest context only while awake, so no other
+    /// commands should be sent until the digest is completed.
+    pub fn sha_start(&mut self) -> Result {
+        self.sha_buffer.clear();
+        self.send_command_retries(&EccCommand::sha(ShaMode::Start, &[]), false, 1)
+            .map(|_| ())
+    }
+
+    /// Adds data to a digest computation started with [`Ecc::sha_start`].
+    /// Data is sent to the chip in 64 byte blocks; any remainder is buffered
+    /// until more data arrives or the digest is completed.
+    pub fn sha_update(&mut self, data: &[u8]) -> Result {
+        self.sha_buffer.extend_from_slice(data);
+        while self.sha_buffer.len() >= ATCA_SHA_BLOCK_SIZE {
+            let block = self.sha_buffer.split_to(ATCA_SHA_BLOCK_SIZE);
+            self.send_command_retries(&EccCommand::sha(ShaMode::Update, &block), false, 1)?;
+        }
+        Ok(())
+    }
+
+    /// Completes a digest computation with the given final data and returns
+    /// the 32 byte SHA256 digest.
+    pub fn sha_end(&mut self, data: &[u8]) -> Result<Bytes> {
+        self.sha_finish(ShaMode::End, data)
+    }
+
+    fn sha_finish(&mut self, mode: ShaMode, data: &[u8]) -> Result<Bytes> {
+        self.sha_update(data)?;
+        let remainder = self.sha_buffer.split();
+        self.send_command_retries(&EccCommand::sha(mode, &remainder), true, 1)
+    }
+
+    /// Computes the HMAC-SHA256 of the given message using the key stored in
+    /// the given slot, and returns the 32 byte MAC. The key never leaves the
+    /// chip.
+    ///
+    /// The slot must be configured for HMAC: its KeyConfig key type must not
+    /// be ECC and the key must be usable by the SHA command. The chip's
+    /// error is returned if it is not.
+    pub fn hmac(&mut self, key_slot: u8, message: &[u8]) -> Result<Bytes> {
+        check_slot(key_slot)?;
+        self.sha_buffer.clear();
+        self.send_command_retries(
+            &EccCommand::sha(ShaMode::HmacStart { key_slot }, &[]),
+            false,
+            1,
+        )?;
+        self.sha_finish(ShaMode::HmacEnd, message)
+    }
+
+    /// Combines the key identified by `zone` and `key_slot` with the current
+    /// TempKey and leaves the resulting digest in TempKey. TempKey must have been set up
+    /// by a prior Nonce.
+    ///
+    /// The optional 4 byte `other_data` is only used with data zone keys
+    /// that are configured as CheckMac copies.
+    ///
+    /// The chip is not put to sleep after this command so that TempKey is
+    /// preserved for the command that uses it.
+    pub fn gen_dig(&mut self, zone: Zone, key_slot: u8, other_data: Option<&[u8]>) -> Result {
+        check_slot(key_slot)?;
+        let other_data = other_data.unwrap_or_default();
+        match zone {
+            Zone::Data if !other_data.is_empty() => check_length(other_data, 4)?,
+            Zone::Config => check_length(other_data, 0)?,
+            _ => (),
+        }
+        self.send_command_retries(&EccCommand::gen_dig(zone, key_slot, other_data), false, 1)
+            .map(|_| ())
+    }
+
+    /// Derives a new key into the target slot by combining the source key
+    /// selected by `mode` with TempKey. The derived key is never visible to
+    /// the host.
+    ///
+    /// TempKey must have been set up with a random Nonce before calling this,
+    /// and the target slot's SlotConfig must permit DeriveKey in the given
+    /// mode without an authorizing MAC.
+    pub fn derive_key(&mut self, target_slot: u8, mode: DeriveKeyMode) -> Result {
+        check_slot(target_slot)?;
+        // read without sleeping to keep TempKey intact
+        let slot_config = self.read_slot_config(target_slot, false)?;
+        let permitted = matches!(
+            (slot_config.write_config(WriteCommand::DeriveKey), mode),
+            (
+                WriteConfig::DeriveKey(DeriveKeyConfig::Roll(false)),
+                DeriveKeyMode::Roll
+            ) | (
+                WriteConfig::DeriveKey(DeriveKeyConfig::Create(false)),
+                DeriveKeyMode::Create
+            )
+        );
+        if !permitted {
+            return Err(Error::invalid_slot_config(
+                target_slot,
+                "derive key not permitted",
+            ));
+        }
+        self.send_command(&EccCommand::derive_key(target_slot))
+            .map(|_| ())
+    }
+
+    /// Writes an externally generated ECC P256 private key into the given
+    /// slot using an encrypted and authorized PrivWrite. The `write_key` is
+    /// the 32 byte key stored in `write_key_slot`, which must match the
+    /// target slot's SlotConfig.WriteKey.
+    pub fn priv_write(
+        &mut self,
+        slot: u8,
+        cleartext_key: &[u8],
+        write_key_slot: u8,
+        write_key: &[u8],
+    ) -> Result {
+        check_slot(slot)?;
+        check_slot(write_key_slot)?;
+        check_length(cleartext_key, ATCA_KEY_SIZE)?;
+        check_length(write_key, ATCA_KEY_SIZE)?;
+        let serial = self.get_serial()?;
+        let session_key = self.session_key(write_key_slot, write_key, &serial)?;
+        // private keys are left padded to 36 bytes
+        let mut padded_key = Secret([0u8; 36]);
+        padded_key[4..].copy_from_slice(cleartext_key);
+        let mac = host::priv_write_mac(&*session_key, slot, &serial, &*padded_key);
+        let mut data = BytesMut::with_capacity(padded_key.len() + mac.len());
+        data.extend_from_slice(&host::encrypt(&*session_key, &*padded_key));
+        data.extend_from_slice(&mac);
+        self.send_command_retries(&EccCommand::priv_write(slot, data.freeze()), true, 1)
+            .map(|_| ())
+    }
+
+    /// Reads the first 32 bytes of a data slot that is configured for
+    /// encrypted reads. The chip encrypts the data with a session key derived
+    /// from the key in `read_key_slot`, and `read_key` is the host copy of that
+    /// key used to decrypt it.
+    pub fn read_encrypted(
+        &mut self,
+        slot: u8,
+        read_key_slot: u8,
+        read_key: &[u8],
+    ) -> Result<Bytes> {
+        check_slot(slot)?;
+        check_slot(read_key_slot)?;
+        check_length(read_key, ATCA_KEY_SIZE)?;
+        let address = Address::data(slot, 0, 0)?;
+        let serial = self.get_serial()?;
+        let session_key = self.session_key(read_key_slot, read_key, &serial)?;
+        let ciphertext = self.send_command_retries(&EccCommand::read(true, address), true, 1)?;
+        check_length(&ciphertext, ATCA_KEY_SIZE)?;
+        Ok(Bytes::from(host::encrypt(&*session_key, &ciphertext)))
+    }
+
+    /// Writes 32 bytes to the first block of a data slot that requires
+    /// encrypted writes. The data is encrypted with a session key derived from
+    /// the key in `write_key_slot`, and `write_key` is the host copy of that
+    /// key.
+    pub fn write_encrypted(
+        &mut self,
+        slot: u8,
+        data: &[u8; 32],
+        write_key_slot: u8,
+        write_key: &[u8],
+    ) -> Result {
+        check_slot(slot)?;
+        check_slot(write_key_slot)?;
+        check_length(write_key, ATCA_KEY_SIZE)?;
+        let address = Address::data(slot, 0, 0)?;
+        let serial = self.get_serial()?;
+        let session_key = self.session_key(write_key_slot, write_key, &serial)?;
+        let mac = host::write_mac(
+            &*session_key,
+            EccCommand::write_encrypted_param1(&address),
+            u16::from(&address).to_be_bytes(),
+            &serial,
+            data,
+        );
+        let mut payload = BytesMut::with_capacity(data.len() + mac.len());
+        payload.extend_from_slice(&host::encrypt(&*session_key, data));
+        payload.extend_from_slice(&mac);
+        self.send_command_retries(
+            &EccCommand::write_encrypted(address, payload.freeze()),
+            true,
+            1,
+        )
+        .map(|_| ())
+    }
+
+    /// Sets up TempKey through a random Nonce followed by a GenDig with the
+    /// given key and returns the resulting session key as computed by the
+    /// host. The chip is left awake so the session key can be used by the
+    /// next command.
+    fn session_key(
+        &mut self,
+        key_slot: u8,
+        key: &[u8],
+        serial: &[u8],
+    ) -> Result<Secret<host::Key>> {
+        let num_in = self.random()?.split_to(ATCA_NONCE_NUMIN_SIZE);
+        let rand_out = self.send_command_retries(
+            &EccCommand::nonce_random(DataBuffer::TempKey, &num_in),
+            false,
+            1,
+        )?;
+        let temp_key = host::nonce_digest(&rand_out, &num_in);
+        self.gen_dig(Zone::Data, key_slot, None)?;
+        Ok(Secret(host::gen_dig_digest(
+            key, 0x02, key_slot, serial, &temp_key,
+        )))
+    }
+
+    /// Checks that the chip holds `host_secret` in `key_slot`: the chip MACs
+    /// a fresh challenge with the key and the response is compared against
+    /// the MAC the host computes with its copy of the secret.
+    ///
+    /// The challenge comes from the host's random source rather than the
+    /// chip, so a device can't answer from a recorded exchange. Use
+    /// [`Ecc::authenticate_challenge`] to supply the challenge instead.
+    pub fn authenticate(&mut self, key_slot: u8, host_secret: &[u8; 32]) -> Result<bool> {
+        let challenge = host::random_challenge()?;
+        self.authenticate_challenge(key_slot, host_secret, &challenge)
+    }
+
+    /// Like [`Ecc::authenticate`] but with a caller supplied challenge, which
+    /// has to be fresh and unpredictable for every call.
+    pub fn authenticate_challenge(
+        &mut self,
+        key_slot: u8,
+        host_secret: &[u8; 32],
+        challenge: &[u8; 32],
+    ) -> Result<bool> {
+        check_slot(key_slot)?;
+        let serial = self.get_serial()?;
+        let response = self.mac(key_slot, challenge)?;
+        let expected = Secret(host::mac_digest(
+            host_secret,
+            challenge,
+            u8::from(&MacMode::Challenge),
+            key_slot,
+            &serial,
+        ));
+        // compare every byte so the timing doesn't tell where they differ
+        Ok(response.len() == expected.len()
+            && response
+                .iter()
+                .zip(expected.iter())
+                .fold(0, |diff, (a, b)| diff | (a ^ b))
+                == 0)
+    }
+
+    /// Computes the SHA256 MAC over the key in the given slot and the given
+    /// 32 byte challenge, and returns the 32 byte digest.
+    pub fn mac(&mut self, key_slot: u8, challenge: &[u8]) -> Result<Bytes> {
+        self.mac_with_mode(MacMode::Challenge, key_slot, challenge)
+    }
+
+    /// Computes the SHA256 MAC over the key in the given slot and the
+    /// challenge selected by `mode`. The challenge must be 32 bytes for
+    /// [`MacMode::Challenge`] and empty for [`MacMode::TempKey`].
+    pub fn mac_with_mode(
+        &mut self,
+        mode: MacMode,
+        key_slot: u8,
+        challenge: &[u8],
+    ) -> Result<Bytes> {
+        check_slot(key_slot)?;
+        match mode {
+            MacMode::Challenge => check_length(challenge, ATCA_KEY_SIZE)?,
+            MacMode::TempKey => check_length(challenge, 0)?,
+        }
+        self.send_command(&EccCommand::mac(mode, key_slot, challenge))
+    }
+
+    /// Verifies a MAC computed elsewhere, typically by another chip's MAC
+    /// command, against the key in the given slot. `challenge` and `response`
+    /// are 32 bytes and the 13 byte `other_data` is passed through to the
+    /// chip verbatim.
+    ///
+    /// Returns `Ok(false)` if the response does not match. Note that a
+    /// mismatch has a side effect on the chip: it decrements the fail
+    /// counter associated with the key.
+    pub fn check_mac(
+        &mut self,
+        key_slot: u8,
+        challenge: &[u8],
+        response: &[u8],
+        other_data: &[u8],
+    ) -> Result<bool> {
+        check_slot(key_slot)?;
+        check_length(challenge, 32)?;
+        check_length(response, 32)?;
+        check_length(other_data, 13)?;
+        verify_result(self.send_command(&EccCommand::check_mac(
+            key_slot, challenge, response, other_data,
+        )))
+    }
+
+    /// Runs the SecureBoot command in Full mode, verifying the 64 byte
+    /// signature over the 32 byte firmware image digest against the
+    /// SecureBoot public key stored on the chip.
+    ///
+    /// Returns `Ok(false)` if the chip rejected the signature.
+    pub fn secure_boot_full(&mut self, digest: &[u8], signature: &[u8]) -> Result<bool> {
+        check_length(digest, 32)?;
+        check_length(signature, 64)?;
+        verify_result(self.send_command(&EccCommand::secure_boot(
+            SecureBootMode::Full,
+            digest,
+            signature,
+        )))
+    }
+
+    /// Updates the UserExtra or UserExtraAdd config byte, which is allowed
+    /// after the config zone is locked, or decrements the limited use counter
+    /// of the slot given as the value.
+    ///
+    /// The UserExtra bytes can only be written once while they are zero, so the
+    /// command is not retried and any chip error is returned as is.
+    pub fn update_extra(&mut self, target: UpdateExtraTarget, value: u8) -> Result {
+        if target == UpdateExtraTarget::Decrement && value > MAX_SLOT {
+            return Err(Error::invalid_slot(value));
+        }
+        self.send_command_retries(&EccCommand::update_extra(target, value), true, 1)
+            .map(|_| ())
+    }
+
+    /// Encrypts a single block with AES-128 in ECB mode using the key in the
+    /// given AES key slot.
+    pub fn aes_encrypt_block(
+        &mut self,
+        key_slot: u8,
+        block: &[u8; ATCA_AES_BLOCK_SIZE],
+    ) -> Result<[u8; ATCA_AES_BLOCK_SIZE]> {
+        self.aes_block(AesMode::Encrypt, key_slot, block)
+    }
+
+    /// Decrypts a single block with AES-128 in ECB mode using the key in the
+    /// given AES key slot.
+    pub fn aes_decrypt_block(
+        &mut self,
+        key_slot: u8,
+        block: &[u8; ATCA_AES_BLOCK_SIZE],
+    ) -> Result<[u8; ATCA_AES_BLOCK_SIZE]> {
+        self.aes_block(AesMode::Decrypt, key_slot, block)
+    }
+
+    fn aes_block(
+        &mut self,
+        mode: AesMode,
+        key_slot: u8,
+        block: &[u8; ATCA_AES_BLOCK_SIZE],
+    ) -> Result<[u8; ATCA_AES_BLOCK_SIZE]> {
+        self.check_aes_slot(key_slot)?;
+        self.aes_command(mode, key_slot, block)
+    }
+
+    /// Encrypts and authenticates `plaintext` with AES-128-GCM using the key in
+    /// the given AES key slot. Returns the ciphertext and the 16 byte tag.
+    ///
+    /// The chip only provides the AES block cipher and the GHASH field
+    /// multiply, so this issues two commands for every 16 bytes of input.
+    pub fn aes_gcm_encrypt(
+        &mut self,
+        key_slot: u8,
+        iv: &[u8; 12],
+        aad: &[u8],
+        plaintext: &[u8],
+    ) -> Result<(Bytes, [u8; ATCA_AES_BLOCK_SIZE])> {
+        self.check_aes_slot(key_slot)?;
+        let h =
+            Secret(self.aes_command(AesMode::Encrypt, key_slot, &[0u8; ATCA_AES_BLOCK_SIZE])?);
+        let j0 = gcm_j0(iv);
+        let ciphertext = self.aes_gcm_ctr(key_slot, &j0, plaintext)?;
+        let tag = self.aes_gcm_tag(key_slot, &h, &j0, aad, &ciphertext)?;
+        Ok((ciphertext, tag))
+    }
+
+    /// Verifies the tag and decrypts `ciphertext` with AES-128-GCM using the
+    /// key in the given AES key slot.
+    ///
+    /// Returns [`Error::AuthenticationFailed`] without decrypting anything if
+    /// the tag does not match.
+    pub fn aes_gcm_decrypt(
+        &mut self,
+        key_slot: u8,
+        iv: &[u8; 12],
+        aad: &[u8],
+        ciphertext: &[u8],
+        tag: &[u8; ATCA_AES_BLOCK_SIZE],
+    ) -> Result<Bytes> {
+        self.check_aes_slot(key_slot)?;
+        let h =
+            Secret(self.aes_command(AesMode::Encrypt, key_slot, &[0u8; ATCA_AES_BLOCK_SIZE])?);
+        let j0 = gcm_j0(iv);
+        let expected = self.aes_gcm_tag(key_slot, &h, &j0, aad, ciphertext)?;
+        // compare without an early exit
+        if expected
+            .iter()
+            .zip(tag)
+            .fold(0, |acc, (a, b)| acc | (a ^ b))
+            != 0
+        {
+            return Err(Error::authentication_failed());
+        }
+        self.aes_gcm_ctr(key_slot, &j0, ciphertext)
+    }
+
+    /// Runs GCTR starting at inc32(J0) over the given data
+    fn aes_gcm_ctr(
+        &mut self,
+        key_slot: u8,
+        j0: &[u8; ATCA_AES_BLOCK_SIZE],
+        data: &[u8],
+    ) -> Result<Bytes> {
+        let mut counter = *j0;
+        let mut result = BytesMut::with_capacity(data.len());
+        for chunk in data.chunks(ATCA_AES_BLOCK_SIZE) {
+            gcm_inc32(&mut counter);
+            let stream = Secret(self.aes_command(AesMode::Encrypt, key_slot, &counter)?);
+            result.extend(chunk.iter().zip(stream.iter()).map(|(a, b)| a ^ b));
+        }
+        Ok(result.freeze())
+    }
+
+    /// Computes the GCM tag over the aad and ciphertext
+    fn aes_gcm_tag(
+        &mut self,
+        key_slot: u8,
+        h: &[u8; ATCA_AES_BLOCK_SIZE],
+        j0: &[u8; ATCA_AES_BLOCK_SIZE],
+        aad: &[u8],
+        ciphertext: &[u8],
+    ) -> Result<[u8; ATCA_AES_BLOCK_SIZE]> {
+        let mut lengths = BytesMut::with_capacity(ATCA_AES_BLOCK_SIZE);
+        lengths.put_u64(aad.len() as u64 * 8);
+        lengths.put_u64(ciphertext.len() as u64 * 8);
+
+        let mut y = [0u8; ATCA_AES_BLOCK_SIZE];
+        let blocks = aad
+            .chunks(ATCA_AES_BLOCK_SIZE)
+            .chain(ciphertext.chunks(ATCA_AES_BLOCK_SIZE))
+            .chain(std::iter::once(&lengths[..]));
+        for block in blocks {
+            // short blocks are zero padded
+            for (y, b) in y.iter_mut().zip(block) {
+                *y ^= b;
+            }
+            let mut data = [0u8; 2 * ATCA_AES_BLOCK_SIZE];
+            data[..ATCA_AES_BLOCK_SIZE].copy_from_slice(h);
+            data[ATCA_AES_BLOCK_SIZE..].copy_from_slice(&y);
+            y = self.aes_command(AesMode::Gfm, 0, &data)?;
+        }
+
+        let mut tag = self.aes_command(AesMode::Encrypt, key_slot, j0)?;
+        for (t, y) in tag.iter_mut().zip(y) {
+            *t ^= y;
+        }
+        Ok(tag)
+    }
+
+    fn check_aes_slot(&mut self, key_slot: u8) -> Result {
+        check_slot(key_slot)?;
+        if self.get_key_config(key_slot)?.key_type() != KeyConfigType::Aes {
+            return Err(Error::invalid_slot_config(key_slot, "not an aes key"));
+        }
+        Ok(())
+    }
+
+    fn aes_command(
+        &mut self,
+        mode: AesMode,
+        key_slot: u8,
+        data: &[u8],
+    ) -> Result<[u8; ATCA_AES_BLOCK_SIZE]> {
+        let bytes = self.send_command(&EccCommand::aes(mode, key_slot, data))?;
+        check_length(&bytes, ATCA_AES_BLOCK_SIZE)?;
+        let mut result = [0u8; ATCA_AES_BLOCK_SIZE];
+        result.copy_from_slice(&bytes);
+        Ok(result)
+    }
+
+    /// Derives a key from the key in `source_slot` with HKDF, using `info` as
+    /// the HKDF info. The first `out_len` bytes of the 32 byte derived key are
+    /// returned.
+    pub fn kdf_hkdf(&mut self, source_slot: u8, info: &[u8], out_len: usize) -> Result<Bytes> {
+        if out_len > 32 {
+            return Err(Error::invalid_length(32, out_len));
+        }
+        let mut key = self.kdf_hkdf_with(KdfSource::Slot(source_slot), KdfTarget::Output, info)?;
+        key.truncate(out_len);
+        Ok(key)
+    }
+
+    /// Derives a key from the given source with HKDF, using `info` as the HKDF
+    /// info (at most 128 bytes), and stores it in the given target.
+    ///
+    /// Returns the derived key if the target is [`KdfTarget::Output`] and
+    /// empty bytes otherwise.
+    pub fn kdf_hkdf_with(
+        &mut self,
+        source: KdfSource,
+        target: KdfTarget,
+        info: &[u8],
+    ) -> Result<Bytes> {
+        if let KdfSource::Slot(slot) = source {
+            check_slot(slot)?;
+        }
+        if let KdfTarget::Slot(slot) = target {
+            check_slot(slot)?;
+        }
+        if info.len() > ATCA_KDF_MESSAGE_MAX {
+            return Err(Error::invalid_length(ATCA_KDF_MESSAGE_MAX, info.len()));
+        }
+        self.send_command(&EccCommand::kdf(KdfAlgorithm::Hkdf, source, target, info))
+    }
+
+    /// Runs the given built-in self tests and reports which of them failed.
+    ///
+    /// The chip reports failed tests as a bitmap in place of the status byte.
+    /// A failed self test leaves the chip in a failure state until the
+    /// failing tests pass on a subsequent run.
+    ///
+    /// The parse, execution and computation fault statuses can't be told
+    /// apart from a bitmap by value and are returned as errors, since they
+    /// mean the tests didn't run.
+    pub fn self_test(&mut self, tests: SelfTestFlags) -> Result<SelfTestResult> {
+        let status = match self.send_command(&EccCommand::self_test(tests)) {
+            Ok(_) => 0,
+            Err(Error::Ecc { opcode, error }) => match error {
+                EccError::SelfTestError => CMD_STATUS_BYTE_SELF_TEST,
+                // SelfTest doesn't compare anything, 0x01 is a failed RNG test
+                EccError::Miscompare | EccError::Unknown(_) => match error.status() {
+                    Some(status) if SelfTestFlags::from_bits(status).is_some() => status,
+                    _ => return Err(Error::ecc(opcode, error)),
+                },
+                _ => return Err(Error::ecc(opcode, error)),
+            },
+            Err(err) => return Err(err),
+        };
+        Ok(SelfTestResult {
+            tested: tests,
+            failed: SelfTestFlags::from_bits_truncate(status) & tests,
+        })
+    }
+
+    /// Runs every built-in self test. See [`Ecc::self_test`].
+    pub fn self_test_all(&mut self) -> Result<SelfTestResult> {
+        self.self_test(SelfTestFlags::all())
+    }
+
     pub fn random(&mut self) -> Result<Bytes> {
         self.send_command(&EccCommand::random())
     }
 
+    /// Like [`Ecc::random`] but returns the 32 random bytes as an array.
+    pub fn random_array(&mut self) -> Result<[u8; 32]> {
+        let bytes = self.random()?;
+        check_length(&bytes, 32)?;
+        let mut random = [0u8; 32];
+        random.copy_from_slice(&bytes);
+        Ok(random)
+    }
+
+    /// Returns `len` random bytes from as many Random commands as needed.
+    /// The chip is woken once for all of them, like [`Ecc::sign_batch`].
+    pub fn random_n(&mut self, len: usize) -> Result<Bytes> {
+        let result = self.random_n_awake(len);
+        if !self.in_session {
+            self.transport.send_sleep();
+        }
+        result
+    }
+
+    fn random_n_awake(&mut self, len: usize) -> Result<Bytes> {
+        let random_duration = self.transport.command_duration(&EccCommand::random());
+        let mut result = BytesMut::with_capacity(len + 32);
+        self.transport.send_wake(self.wake_delay)?;
+        let mut awake = Instant::now();
+        while result.len() < len {
+            if awake.elapsed() + random_duration > WATCHDOG_BUDGET {
+                self.transport.send_sleep();
+                self.transport.send_wake(self.wake_delay)?;
+                awake = Instant::now();
+            }
+            let random = self.send_command_inner(&EccCommand::random(), false, false, 1)?;
+            check_length(&random, 32)?;
+            result.extend_from_slice(&random);
+        }
+        result.truncate(len);
+        Ok(result.freeze())
+    }
+
+    /// Returns 32 random bytes like [`Ecc::random`] but without updating the
+    /// random seed in EEPROM. This is faster and avoids EEPROM wear, but the
+    /// seed should still be updated periodically with [`Ecc::random`] to keep
+    /// up the quality of the generated numbers.
+    pub fn random_no_seed_update(&mut self) -> Result<Bytes> {
+        self.send_command(&EccCommand::random_no_seed_update())
+    }
+
+    /// Loads the given 32 or 64 byte data into the target buffer as is. This
+    /// is the same as [`Ecc::nonce_passthrough`] but also accepts 64 bytes.
     pub fn nonce(&mut self, target: DataBuffer, data: &[u8]) -> Result {
         self.send_command(&EccCommand::nonce(target, Bytes::copy_from_slice(data)))
             .map(|_| ())
     }
 
+    /// Loads the given 32 bytes into the target buffer unchanged.
+    ///
+    /// The chip is left awake since sleeping clears the buffer.
+    pub fn nonce_passthrough(&mut self, target: DataBuffer, data: &[u8; 32]) -> Result {
+        self.send_command_retries(
+            &EccCommand::nonce(target, Bytes::copy_from_slice(data)),
+            false,
+            1,
+        )
+        .map(|_| ())
+    }
+
+    /// Sets the target buffer to the SHA256 digest of a random number
+    /// generated by the chip, the 20 byte `num_in` and the nonce parameters.
+    /// Returns the 32 byte random number so the host can compute the same
+    /// digest.
+    ///
+    /// The chip is left awake since sleeping clears the buffer.
+    pub fn nonce_random(&mut self, target: DataBuffer, num_in: &[u8; 20]) -> Result<Bytes> {
+        self.send_command_retries(&EccCommand::nonce_random(target, num_in), false, 1)
+    }
+
+    /// Reads a public key stored in the 72 byte padded layout from the given
+    /// data slot. Only slots 8 to 15 are large enough to hold a public key.
+    pub fn read_pubkey(&mut self, slot: u8) -> Result<PublicKey> {
+        if slot_size(slot)? < PUBLIC_KEY_SLOT_SIZE {
+            return Err(Error::invalid_slot(slot));
+        }
+        PublicKey::from_slot_bytes(&self.read_bytes(slot, 0, PUBLIC_KEY_SLOT_SIZE)?)
+    }
+
+    /// Writes a public key to the given data slot using the 72 byte padded
+    /// layout. Only slots 8 to 15 are large enough to hold a public key.
+    pub fn write_pubkey(&mut self, slot: u8, key: &PublicKey) -> Result {
+        if slot_size(slot)? < PUBLIC_KEY_SLOT_SIZE {
+            return Err(Error::invalid_slot(slot));
+        }
+        self.write_bytes(slot, 0, &key.to_slot_bytes())
+    }
+
+    /// Reads data from a data slot starting at the given byte offset, split
+    /// into 32 byte block reads where the data covers a whole block and 4
+    /// byte word reads elsewhere. The offset and the length have to be
+    /// multiples of 4 and the data has to be within the slot.
+    pub fn read_bytes(&mut self, slot: u8, offset: usize, len: usize) -> Result<Bytes> {
+        if (offset | len) & 3 != 0 || offset + len > slot_size(slot)? {
+            return Err(Error::invalid_address());
+        }
+        let mut result = BytesMut::with_capacity(len);
+        let (mut pos, end) = (offset, offset + len);
+        while pos < end {
+            let (block, word) = ((pos / 32) as u8, (pos % 32 / 4) as u8);
+            let read_32 = word == 0 && end - pos >= 32;
+            result.extend_from_slice(&self.read(read_32, &Address::data(slot, block, word)?)?);
+            pos += if read_32 { 32 } else { 4 };
+        }
+        Ok(result.freeze())
+    }
+
+    /// Reads the whole contents of a data slot, sized to the slot's
+    /// capacity: 13 blocks for the 416 byte slot 8.
+    pub fn read_slot(&mut self, slot: u8) -> Result<Bytes> {
+        self.read_bytes(slot, 0, slot_size(slot)?)
+    }
+
+    /// Writes data to a data slot starting at the given byte offset, split
+    /// into 32 byte block writes where the data covers a whole block and 4
+    /// byte word writes elsewhere. The offset and the data length have to be
+    /// multiples of 4 and the data has to fit in the slot.
+    pub fn write_bytes(&mut self, slot: u8, offset: usize, data: &[u8]) -> Result {
+        self.write_chunks(slot_size(slot)?, offset, data, |block, word| {
+            Address::data(slot, block, word)
+        })
+    }
+
+    /// Reads one of the two 32 byte blocks of the 64 byte OTP zone.
+    pub fn read_otp(&mut self, block: u8) -> Result<Bytes> {
+        if block > 1 {
+            return Err(Error::invalid_address());
+        }
+        self.read(true, &Address::otp(block, 0)?)
+    }
+
+    /// Writes data to the OTP zone starting at the given byte offset, split
+    /// the same way as [`write_bytes`](Self::write_bytes).
+    ///
+    /// OTP writes are one way. Before the data zone is locked the whole zone
+    /// can be written freely, afterwards the OTP mode in the config zone
+    /// decides: in read only mode every write fails, and in consumption mode
+    /// bits can only be cleared, never set again. Refused writes come back as
+    /// [`Error::Ecc`] with the chip's status.
+    pub fn write_otp(&mut self, offset: usize, data: &[u8]) -> Result {
+        self.write_chunks(OTP_ZONE_SIZE, offset, data, Address::otp)
+    }
+
+    /// Writes data at a byte offset into a zone area of the given size using
+    /// the largest writes the alignment allows.
+    fn write_chunks<F>(&mut self, size: usize, offset: usize, data: &[u8], address: F) -> Result
+    where
+        F: Fn(u8, u8) -> Result<Address>,
+    {
+        if (offset | data.len()) & 3 != 0 || offset + data.len() > size {
+            return Err(Error::invalid_address());
+        }
+        let mut pos = offset;
+        let mut data = data;
+        while !data.is_empty() {
+            let (block, word) = ((pos / 32) as u8, (pos % 32 / 4) as u8);
+            let len = if word == 0 && data.len() >= 32 { 32 } else { 4 };
+            let (chunk, rest) = data.split_at(len);
+            self.write(&address(block, word)?, chunk)?;
+            pos += len;
+            data = rest;
+        }
+        Ok(())
+    }
+
     pub fn read(&mut self, read_32: bool, address: &Address) -> Result<Bytes> {
         self.send_command(&EccCommand::read(read_32, address.clone()))
     }
@@ -151,8 +1677,39 @@ impl Ecc {
             .map(|_| ())
     }
 
+    /// Sends a command with the configured retries, giving up with
+    /// [`Error::Timeout`] once `timeout` has passed instead of waiting the
+    /// typical execution time of the command. The response is read after
+    /// at most `timeout` and the transport stops polling for it at the
+    /// deadline. Whatever fails after the deadline, including a chip still
+    /// busy with the command, is reported as [`Error::Timeout`].
+    pub fn send_command_with_timeout(
+        &mut self,
+        command: &EccCommand,
+        timeout: Duration,
+    ) -> Result<Bytes> {
+        let deadline = Instant::now() + timeout;
+        self.deadline = Some(deadline);
+        self.transport.set_deadline(Some(deadline));
+        let result = self.send_command(command);
+        self.transport.set_deadline(None);
+        self.deadline = None;
+        result
+    }
+
+    fn deadline_passed(&self) -> bool {
+        self.deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+    }
+
+    /// Like [`Ecc::random`], see
+    /// [`send_command_with_timeout`](Self::send_command_with_timeout).
+    pub fn random_with_timeout(&mut self, timeout: Duration) -> Result<Bytes> {
+        self.send_command_with_timeout(&EccCommand::random(), timeout)
+    }
+
     pub(crate) fn send_command(&mut self, command: &EccCommand) -> Result<Bytes> {
-        self.send_command_retries(command, true, CMD_RETRIES)
+        self.send_command_retries(command, true, self.retries)
     }
 
     pub(crate) fn send_command_retries(
@@ -161,34 +1718,742 @@ impl Ecc {
         sleep: bool,
         retries: u8,
     ) -> Result<Bytes> {
-        let mut buf = BytesMut::with_capacity(ATCA_CMD_SIZE_MAX as usize);
+        self.send_command_inner(command, true, sleep, retries)
+    }
+
+    /// Sends a command, waking the chip before each attempt if `wake` is set
+    /// and putting it to sleep after a response if `sleep` is set.
+    fn send_command_inner(
+        &mut self,
+        command: &EccCommand,
+        wake: bool,
+        sleep: bool,
+        retries: u8,
+    ) -> Result<Bytes> {
+        #[cfg(feature = "tracing")]
+        let span = tracing::debug_span!(
+            "ecc_command",
+            opcode = command.opcode(),
+            retry = tracing::field::Empty
+        )
+        .entered();
+        // the frames carry keys written in the clear and secrets returned
+        let mut buf = Secret(BytesMut::with_capacity(ATCA_CMD_SIZE_MAX as usize));
+        let mut wake = wake;
         for retry in 0..retries {
+            #[cfg(feature = "tracing")]
+            span.record("retry", retry);
+            if retry > 0 {
+                let mut backoff = self.backoff(retry);
+                if let Some(deadline) = self.deadline {
+                    backoff = backoff.min(deadline.saturating_duration_since(Instant::now()));
+                }
+                thread::sleep(backoff);
+            }
+            if self.deadline_passed() {
+                trace_event!(warn, retry, "deadline passed");
+                return Err(Error::timeout());
+            }
             buf.clear();
             buf.put_u8(self.transport.put_command_flag());
             command.bytes_into(&mut buf);
 
-            self.transport.send_wake()?;
+            if wake {
+                trace_event!(trace, "wake");
+                self.transport.send_wake(self.wake_delay)?;
+            }
 
-            let delay = self.transport.command_duration(command);
+            let mut delay = self.transport.command_duration(command);
+            if let Some(deadline) = self.deadline {
+                delay = delay.min(deadline.saturating_duration_since(Instant::now()));
+            }
 
-            if let Err(_err) = self.transport.send_recv_buf(delay, &mut buf) {
-                if retry == retries {
-                    break;
-                } else {
+            #[cfg(feature = "wire-debug")]
+            log::trace!("tx opcode {:#04x}: {}", command.opcode(), wire_hex(&buf));
+            match self.transport.send_recv_buf(delay, &mut buf) {
+                Ok(()) => (),
+                Err(_err) if self.deadline_passed() => {
+                    trace_event!(warn, error = %_err, "deadline passed");
+                    return Err(Error::timeout());
+                }
+                Err(err) if err.is_retryable() => {
+                    trace_event!(debug, error = %err, "transport error");
                     continue;
                 }
+                Err(err) => {
+                    trace_event!(warn, error = %err, "transport failed");
+                    return Err(err);
+                }
             }
+            #[cfg(feature = "wire-debug")]
+            log::trace!("rx opcode {:#04x}: {}", command.opcode(), wire_hex(&buf));
 
-            let response = EccResponse::from_bytes(&buf[..])?;
-            if sleep {
+            let response = EccResponse::from_bytes(&buf[..]);
+            if sleep && !self.in_session {
                 self.transport.send_sleep();
             }
+            // a corrupted frame is a bus glitch, retry it like a transport
+            // error
+            let response = match response {
+                Err(Error::Crc) => {
+                    trace_event!(debug, "crc error");
+                    continue;
+                }
+                response => response?,
+            };
             match response {
-                EccResponse::Data(bytes) => return Ok(bytes),
-                EccResponse::Error(err) if err.is_recoverable() && retry < retries => continue,
-                EccResponse::Error(err) => return Err(Error::ecc(err)),
+                EccResponse::Data(bytes) => {
+                    trace_event!(debug, "success");
+                    return Ok(bytes);
+                }
+                // the chip fell asleep before it got to the command, it has
+                // to be woken again before the command is reissued
+                EccResponse::Error(EccError::WatchDogError) if retry + 1 < retries => {
+                    trace_event!(debug, "watchdog expired");
+                    wake = true;
+                    continue;
+                }
+                // self test failures are reported in the status byte and
+                // won't go away on a retry
+                EccResponse::Error(err)
+                    if err.is_recoverable()
+                        && !matches!(command, EccCommand::SelfTest { .. })
+                        && retry + 1 < retries =>
+                {
+                    trace_event!(debug, error = ?err, "recoverable error");
+                    continue;
+                }
+                EccResponse::Error(err) => {
+                    trace_event!(warn, error = ?err, "command failed");
+                    return Err(Error::ecc(command.opcode(), err));
+                }
             }
         }
+        trace_event!(warn, retries, "timeout");
         Err(Error::timeout())
     }
 }
+
+#[cfg(feature = "wire-debug")]
+fn wire_hex(buf: &[u8]) -> String {
+    buf.iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Returns the size in bytes of the given data slot: 36 bytes for slots 0
+/// to 7, 416 bytes for slot 8 and 72 bytes for slots 9 to 15.
+pub fn slot_size(slot: u8) -> Result<usize> {
+    match slot {
+        0..=7 => Ok(36),
+        8 => Ok(416),
+        9..=MAX_SLOT => Ok(72),
+        _ => Err(Error::invalid_slot(slot)),
+    }
+}
+
+/// Rejects slots above [`MAX_SLOT`] before anything is sent to the chip.
+fn check_slot(slot: u8) -> Result {
+    if slot > MAX_SLOT {
+        return Err(Error::invalid_slot(slot));
+    }
+    Ok(())
+}
+
+fn check_length(data: &[u8], expected: usize) -> Result {
+    if data.len() != expected {
+        return Err(Error::invalid_length(expected, data.len()));
+    }
+    Ok(())
+}
+
+fn gcm_j0(iv: &[u8; 12]) -> [u8; ATCA_AES_BLOCK_SIZE] {
+    let mut j0 = [0u8; ATCA_AES_BLOCK_SIZE];
+    j0[..12].copy_from_slice(iv);
+    j0[15] = 1;
+    j0
+}
+
+fn gcm_inc32(counter: &mut [u8; ATCA_AES_BLOCK_SIZE]) {
+    let mut tail = [0u8; 4];
+    tail.copy_from_slice(&counter[12..]);
+    let next = u32::from_be_bytes(tail).wrapping_add(1);
+    counter[12..].copy_from_slice(&next.to_be_bytes());
+}
+
+fn verify_result(result: Result<Bytes>) -> Result<bool> {
+    match result {
+        Ok(_) => Ok(true),
+        Err(Error::Ecc {
+            error: EccError::Miscompare,
+            ..
+        }) => Ok(false),
+        Err(err) => Err(err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::MockTransport;
+
+    fn mock_ecc(responses: Vec<Option<Vec<u8>>>) -> Ecc<MockTransport> {
+        Ecc::new(MockTransport {
+            responses: responses.into(),
+            ..Default::default()
+        })
+    }
+
+    fn mock_attempts(ecc: &Ecc<MockTransport>) -> usize {
+        ecc.transport.attempts
+    }
+
+    fn mock_sleeps(ecc: &Ecc<MockTransport>) -> usize {
+        ecc.transport.sleeps
+    }
+
+    // a successful empty response, a recoverable comms error status, a
+    // success status with a corrupted crc and a watchdog expiry status
+    const SUCCESS: [u8; 4] = [0x04, 0x00, 0x03, 0x40];
+    const COMMS_ERROR: [u8; 4] = [0x04, 0xFF, 0x01, 0x42];
+    const CRC_ERROR: [u8; 4] = [0x04, 0x00, 0x03, 0x41];
+    const WATCHDOG_ERROR: [u8; 4] = [0x04, 0xEE, 0x31, 0x41];
+
+    #[test]
+    fn retries_transport_errors() {
+        let mut ecc = mock_ecc(vec![None, None, Some(SUCCESS.to_vec())]).with_retries(3);
+        assert!(ecc.send_command(&EccCommand::random()).is_ok());
+        assert_eq!(3, mock_attempts(&ecc));
+
+        let mut ecc = mock_ecc(vec![None, None, None, None]).with_retries(3);
+        assert!(matches!(
+            ecc.send_command(&EccCommand::random()),
+            Err(Error::Timeout)
+        ));
+        assert_eq!(3, mock_attempts(&ecc));
+    }
+
+    #[test]
+    fn retries_recoverable_errors() {
+        let mut ecc = mock_ecc(vec![
+            Some(COMMS_ERROR.to_vec()),
+            Some(COMMS_ERROR.to_vec()),
+            Some(SUCCESS.to_vec()),
+        ])
+        .with_retries(3);
+        assert!(ecc.send_command(&EccCommand::random()).is_ok());
+        assert_eq!(3, mock_attempts(&ecc));
+
+        let mut ecc = mock_ecc(vec![Some(COMMS_ERROR.to_vec()); 4]).with_retries(3);
+        let err = ecc.send_command(&EccCommand::random()).unwrap_err();
+        assert!(matches!(
+            err,
+            Error::Ecc {
+                opcode: crate::constants::ATCA_RANDOM,
+                error: EccError::CommsError,
+            }
+        ));
+        // the chip's status is the source of the command error
+        assert_eq!("ecc command 0x1b failed", err.to_string());
+        let source = std::error::Error::source(&err).expect("source");
+        assert_eq!("command not received correctly", source.to_string());
+        assert_eq!(3, mock_attempts(&ecc));
+    }
+
+    #[test]
+    fn sign_frames() {
+        const RANDOM: [u8; 8] = [0x03, 0x07, 0x1B, 0x00, 0x00, 0x00, 0x24, 0xCD];
+        // passthrough nonce of SHA256("message") into the message digest buffer
+        let mut nonce = vec![0x03, 0x27, 0x16, 0x43, 0x00, 0x00];
+        nonce.extend_from_slice(&Sha256::digest(b"message"));
+        nonce.extend_from_slice(&[0xB8, 0x43]);
+        let mock = MockTransport::default()
+            .expect(&RANDOM, &[0x42; 32])
+            .expect(&nonce, &[0x00])
+            .expect(
+                &[0x03, 0x07, 0x41, 0xA0, 0x02, 0x00, 0x7D, 0x05],
+                &[0x11; 64],
+            );
+        let mut ecc = Ecc::new(mock);
+        assert_eq!([0x11; 64], ecc.sign_array(2, b"message").expect("sign"));
+
+        // one retry after a transport error, then the attempts run out
+        let mock = MockTransport::default()
+            .expect_error(&RANDOM)
+            .expect(&RANDOM, &[0x42; 32])
+            .expect_error(&RANDOM)
+            .expect_error(&RANDOM);
+        let mut ecc = Ecc::new(mock).with_retries(2);
+        assert!(ecc.random().is_ok());
+        assert!(ecc.random().is_err());
+        assert_eq!(4, ecc.transport().sent.len());
+    }
+
+    #[test]
+    fn command_deadline() {
+        let mut ecc = mock_ecc(vec![None, None, MockTransport::frame(&[0x42; 32])])
+            .with_retries(3)
+            .with_backoff(Duration::from_millis(50), Duration::from_millis(50));
+        assert!(matches!(
+            ecc.random_with_timeout(Duration::from_millis(80)),
+            Err(Error::Timeout)
+        ));
+        assert_eq!(2, mock_attempts(&ecc));
+        // the deadline only applies to the one command
+        assert!(ecc.random().is_ok());
+    }
+
+    #[test]
+    fn fails_after_deadline() {
+        // a chip still busy with the command when the deadline passes, which
+        // isn't retried without a deadline
+        let busy = || MockTransport {
+            responses: vec![None].into(),
+            errors: vec![Error::NoDevice].into(),
+            latency: Duration::from_millis(60),
+            ..Default::default()
+        };
+        let mut ecc = Ecc::new(busy()).with_retries(3);
+        assert!(matches!(
+            ecc.random_with_timeout(Duration::from_millis(30)),
+            Err(Error::Timeout)
+        ));
+        assert_eq!(1, mock_attempts(&ecc));
+        let mut ecc = Ecc::new(busy()).with_retries(3);
+        assert!(matches!(ecc.random(), Err(Error::NoDevice)));
+    }
+
+    #[test]
+    fn retries_crc_errors() {
+        let mut ecc = mock_ecc(vec![Some(CRC_ERROR.to_vec()), Some(SUCCESS.to_vec())]);
+        assert!(ecc.send_command(&EccCommand::random()).is_ok());
+        assert_eq!(2, mock_attempts(&ecc));
+
+        let mut ecc = mock_ecc(vec![Some(CRC_ERROR.to_vec()); 4]).with_retries(3);
+        assert!(matches!(
+            ecc.send_command(&EccCommand::random()),
+            Err(Error::Timeout)
+        ));
+        assert_eq!(3, mock_attempts(&ecc));
+    }
+
+    #[test]
+    fn rewakes_after_watchdog() {
+        let mut ecc = mock_ecc(vec![Some(WATCHDOG_ERROR.to_vec()), Some(SUCCESS.to_vec())]);
+        assert!(ecc
+            .send_command_inner(&EccCommand::random(), false, true, 2)
+            .is_ok());
+        assert_eq!(1, ecc.transport.wakes);
+    }
+
+    #[test]
+    fn sha_chunks() {
+        // the two block SHA256 test vector from FIPS 180-2
+        const MESSAGE: &[u8] = b"abcdefghbcdefghicdefghijdefghijkefghijklfghijklmghijklmn\
+            hijklmnoijklmnopjklmnopqklmnopqrlmnopqrsmnopqrstnopqrstu";
+        const DIGEST: [u8; 32] = [
+            0xCF, 0x5B, 0x16, 0xA7, 0x78, 0xAF, 0x83, 0x80, 0x03, 0x6C, 0xE5, 0x9E, 0x7B, 0x04,
+            0x92, 0x37, 0x0B, 0x24, 0x9B, 0x11, 0xE8, 0xF0, 0x7A, 0x51, 0xAF, 0xAC, 0x45, 0x03,
+            0x7A, 0xFE, 0xE9, 0xD1,
+        ];
+        assert_eq!(&DIGEST[..], &Sha256::digest(MESSAGE)[..]);
+
+        let mut responses = vec![Some(SUCCESS.to_vec()); 2];
+        responses.push(MockTransport::frame(&DIGEST));
+        let mut ecc = mock_ecc(responses);
+        ecc.sha_start().expect("sha start");
+        ecc.sha_update(&MESSAGE[..40]).expect("sha update");
+        ecc.sha_update(&MESSAGE[40..100]).expect("sha update");
+        assert_eq!(
+            &DIGEST[..],
+            &ecc.sha_end(&MESSAGE[100..]).expect("sha end")[..]
+        );
+
+        // start, one full 64 byte block and the 48 byte tail
+        let sent = &ecc.transport.sent;
+        assert_eq!(3, sent.len());
+        assert_eq!(&[0x00, 0x00], &sent[0][3..5]);
+        assert_eq!(&[0x01, 64], &sent[1][3..5]);
+        assert_eq!(&MESSAGE[..64], &sent[1][6..70]);
+        assert_eq!(&[0x02, 48], &sent[2][3..5]);
+        assert_eq!(&MESSAGE[64..], &sent[2][6..54]);
+    }
+
+    #[test]
+    fn hmac_modes() {
+        let mut responses = vec![Some(SUCCESS.to_vec()); 3];
+        responses.push(MockTransport::frame(&[0x42; 32]));
+        let mut ecc = mock_ecc(responses);
+        assert_eq!(&[0x42; 32], &ecc.hmac(5, &[0x5A; 150]).expect("hmac")[..]);
+        // HMAC start with the key slot, two full blocks and the HMAC end
+        let modes: Vec<_> = ecc.transport.sent.iter().map(|frame| frame[3]).collect();
+        assert_eq!(vec![0x04, 0x01, 0x01, 0x05], modes);
+        assert_eq!(5, ecc.transport.sent[0][4]);
+        assert_eq!(22, ecc.transport.sent[3][4]);
+    }
+
+    #[test]
+    fn authenticate() {
+        let mut config = [0u8; 32];
+        config[..4].copy_from_slice(&[0x01, 0x23, 0x11, 0x22]);
+        config[8..13].copy_from_slice(&[0x33, 0x44, 0x55, 0x66, 0xEE]);
+        let serial = [0x01, 0x23, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0xEE];
+        let (secret, challenge) = ([0x01; 32], [0x02; 32]);
+        let mut response = host::mac_digest(&secret, &challenge, 0x00, 3, &serial);
+
+        let mut ecc = mock_ecc(vec![
+            MockTransport::frame(&config),
+            MockTransport::frame(&response),
+        ]);
+        assert!(ecc
+            .authenticate_challenge(3, &secret, &challenge)
+            .expect("authenticate"));
+        // the challenge is sent as the MAC command data
+        assert_eq!(&challenge, &ecc.transport.sent[1][6..38]);
+
+        response[31] ^= 0x01;
+        let mut ecc = mock_ecc(vec![
+            MockTransport::frame(&config),
+            MockTransport::frame(&response),
+        ]);
+        assert!(!ecc
+            .authenticate_challenge(3, &secret, &challenge)
+            .expect("authenticate"));
+    }
+
+    #[test]
+    fn sign_batch_rewakes() {
+        let signed = [
+            MockTransport::frame(&[0x42; 32]),
+            MockTransport::frame(&[0x00]),
+            MockTransport::frame(&[0x11; 64]),
+        ];
+        let mut ecc = mock_ecc(signed.iter().cloned().cycle().take(9).collect());
+        // no budget left after a signature, so the chip is woken for each
+        let signatures = ecc
+            .sign_batch_awake(2, &[[1; 32], [2; 32], [3; 32]], Duration::ZERO)
+            .expect("sign batch");
+        assert_eq!(3, signatures.len());
+        assert_eq!(3, ecc.transport.wakes);
+        assert_eq!(2, mock_sleeps(&ecc));
+        // Random ahead of the Nonce after every wake
+        let opcodes: Vec<_> = ecc.transport.sent.iter().map(|frame| frame[2]).collect();
+        assert_eq!([0x1B, 0x16, 0x41].repeat(3), opcodes);
+    }
+
+    #[test]
+    fn session_keeps_awake() {
+        let mut ecc = mock_ecc(vec![Some(SUCCESS.to_vec()); 3]);
+        {
+            let mut session = ecc.session();
+            assert!(session.random().is_ok());
+            assert!(session.random().is_ok());
+            assert_eq!(0, mock_sleeps(&session));
+        }
+        assert_eq!(1, mock_sleeps(&ecc));
+        assert!(ecc.random().is_ok());
+        assert_eq!(2, mock_sleeps(&ecc));
+    }
+
+    #[test]
+    fn builder_requires_path_and_address() {
+        let missing = |result: Result<Ecc>| matches!(result, Err(Error::InvalidAddress));
+        assert!(missing(Ecc::builder().address(0x60).retries(3).build()));
+        assert!(missing(Ecc::builder().path("/dev/i2c-1").build()));
+    }
+
+    #[test]
+    fn slot_locked_bits() {
+        // SlotLocked with only slot 0 locked, followed by ChipOptions
+        let word = MockTransport::frame(&[0xFE, 0xFF, 0x00, 0x00]);
+        let mut ecc = mock_ecc(vec![word.clone(), word]);
+        assert!(ecc.is_slot_locked(0).expect("slot locked"));
+        assert!(!ecc.is_slot_locked(1).expect("slot locked"));
+    }
+
+    #[test]
+    fn rejects_invalid_slots() {
+        let mut ecc = mock_ecc(vec![]);
+        let invalid = |result: Result<_>| matches!(result, Err(Error::InvalidSlot(16)));
+        assert!(invalid(ecc.genkey(KeyType::Private, 16).map(|_| ())));
+        assert!(invalid(ecc.get_slot_config(16).map(|_| ())));
+        assert!(invalid(ecc.set_key_config(16, &KeyConfig::default())));
+        assert!(invalid(ecc.sign(16, b"hello").map(|_| ())));
+        assert!(invalid(ecc.is_slot_locked(16).map(|_| ())));
+        // nothing reached the chip
+        assert_eq!(0, mock_attempts(&ecc));
+    }
+
+    #[test]
+    fn ecdh_kdf() {
+        // ECDH into TempKey only answers with a status
+        let mut ecc = mock_ecc(vec![
+            MockTransport::frame(&[0x00]),
+            MockTransport::frame(&[0x42; 32]),
+        ]);
+        let peer = PublicKey {
+            x: [1; 32],
+            y: [2; 32],
+        };
+        assert_eq!([0x42; 32], ecc.ecdh_kdf(0, &peer, b"info").expect("key"));
+        assert!(ecc.ecdh_kdf(0, &peer, &[0; 129]).is_err());
+    }
+
+    #[test]
+    fn counter_exhausted() {
+        // an execution error, then the counter read back
+        let mut ecc = mock_ecc(vec![
+            MockTransport::frame(&[0x0F]),
+            MockTransport::frame(&MAX_COUNTER_VALUE.to_le_bytes()),
+            MockTransport::frame(&[0x0F]),
+            MockTransport::frame(&5u32.to_le_bytes()),
+        ]);
+        assert!(matches!(
+            ecc.counter_increment(1),
+            Err(Error::CounterExhausted(1))
+        ));
+        assert!(matches!(
+            ecc.counter_increment(1),
+            Err(Error::Ecc {
+                error: EccError::ExecError,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn self_test_status() {
+        let mut ecc = mock_ecc(
+            [0x00, 0x07, 0x08, 0x03, 0x0F]
+                .iter()
+                .map(|status| MockTransport::frame(&[*status]))
+                .collect(),
+        )
+        .with_retries(1);
+        assert!(ecc.self_test_all().expect("self test").passed());
+        assert_eq!(
+            SelfTestFlags::RNG | SelfTestFlags::ECDSA,
+            ecc.self_test_all().expect("self test").failed
+        );
+        assert_eq!(
+            SelfTestFlags::ECDH,
+            ecc.self_test_all().expect("self test").failed
+        );
+        // parse and execution errors aren't failure bitmaps
+        assert!(matches!(
+            ecc.self_test_all(),
+            Err(Error::Ecc {
+                error: EccError::ParseError,
+                ..
+            })
+        ));
+        assert!(matches!(
+            ecc.self_test_all(),
+            Err(Error::Ecc {
+                error: EccError::ExecError,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn random_n() {
+        let mut ecc = mock_ecc(vec![
+            MockTransport::frame(&[1; 32]),
+            MockTransport::frame(&[2; 32]),
+            MockTransport::frame(&[3; 32]),
+        ]);
+        let random = ecc.random_n(70).expect("random");
+        assert_eq!(70, random.len());
+        assert_eq!(&[3; 6], &random[64..]);
+        assert_eq!(1, ecc.transport.wakes);
+        assert_eq!(1, mock_sleeps(&ecc));
+    }
+
+    #[test]
+    fn persistent_latch() {
+        let mock = MockTransport::default()
+            .expect(
+                &[0x03, 0x07, 0x30, 0x04, 0x00, 0x00, 0x80, 0xDF],
+                &[0x01, 0x00, 0x00, 0x00],
+            )
+            .expect(
+                &[0x03, 0x07, 0x30, 0x04, 0x02, 0x00, 0x86, 0x5F],
+                &[0x00, 0x00, 0x00, 0x00],
+            )
+            // execution error, no authorization in effect
+            .expect(&[0x03, 0x07, 0x30, 0x04, 0x03, 0x00, 0x8F, 0xDF], &[0x0F]);
+        let mut ecc = Ecc::new(mock).with_retries(1);
+        assert!(ecc.persistent_latch().expect("latch"));
+        ecc.set_persistent_latch(false).expect("cleared");
+        assert!(ecc.set_persistent_latch(true).is_err());
+        assert_eq!(3, ecc.transport().sent.len());
+    }
+
+    #[test]
+    fn measure_latency() {
+        let mut ecc = mock_ecc(vec![MockTransport::frame(&[0x00, 0x00, 0x60, 0x03]); 3]);
+        let stats = ecc.measure_latency(3).expect("latency");
+        assert_eq!(3, stats.iterations);
+        assert!(stats.min <= stats.mean && stats.mean <= stats.max);
+        assert_eq!(stats.max, stats.p99);
+        assert_eq!(3, mock_sleeps(&ecc));
+
+        let mut samples: Vec<_> = (1..=200).rev().map(Duration::from_millis).collect();
+        let stats = LatencyStats::from_samples(&mut samples);
+        assert_eq!(Duration::from_millis(1), stats.min);
+        assert_eq!(Duration::from_millis(198), stats.p99);
+        assert_eq!(Duration::from_micros(100_500), stats.mean);
+    }
+
+    #[test]
+    fn random_array_length() {
+        let mut ecc = mock_ecc(vec![
+            MockTransport::frame(&[0x42; 32]),
+            MockTransport::frame(&[0x42; 16]),
+        ]);
+        assert_eq!([0x42; 32], ecc.random_array().expect("random"));
+        assert!(matches!(
+            ecc.random_array(),
+            Err(Error::InvalidLength {
+                expected: 32,
+                got: 16
+            })
+        ));
+    }
+
+    #[test]
+    fn write_bytes_chunks() {
+        // 7 words up to the first block boundary, 2 blocks and a final word
+        let mut ecc = mock_ecc(vec![Some(SUCCESS.to_vec()); 10]);
+        ecc.write_bytes(8, 4, &[0x5A; 96]).expect("write bytes");
+        assert_eq!(10, mock_attempts(&ecc));
+
+        assert!(ecc.write_bytes(9, 2, &[0; 4]).is_err());
+        assert!(ecc.write_bytes(9, 64, &[0; 12]).is_err());
+    }
+
+    #[test]
+    fn read_bytes_chunks() {
+        // a word, a block and a word
+        let mut ecc = mock_ecc(vec![
+            MockTransport::frame(&[1; 4]),
+            MockTransport::frame(&[2; 32]),
+            MockTransport::frame(&[3; 4]),
+        ]);
+        let bytes = ecc.read_bytes(9, 28, 40).expect("read bytes");
+        assert_eq!(&[1; 4], &bytes[..4]);
+        assert_eq!(&[2; 32], &bytes[4..36]);
+        assert_eq!(&[3; 4], &bytes[36..]);
+
+        assert!(ecc.read_bytes(7, 32, 8).is_err());
+        assert!(matches!(slot_size(16), Err(Error::InvalidSlot(16))));
+    }
+
+    #[test]
+    fn pubkey_round_trip() {
+        let key = PublicKey {
+            x: [1; 32],
+            y: [2; 32],
+        };
+        let slot_bytes = key.to_slot_bytes();
+        let mut responses = vec![Some(SUCCESS.to_vec()); 4];
+        responses.push(MockTransport::frame(&slot_bytes[..32]));
+        responses.push(MockTransport::frame(&slot_bytes[32..64]));
+        responses.push(MockTransport::frame(&slot_bytes[64..68]));
+        responses.push(MockTransport::frame(&slot_bytes[68..]));
+        let mut ecc = mock_ecc(responses);
+        ecc.write_pubkey(9, &key).expect("write pubkey");
+        assert_eq!(key, ecc.read_pubkey(9).expect("read pubkey"));
+
+        // two block writes and two word writes for the last 8 bytes
+        let writes = &ecc.transport.sent[..4];
+        for (frame, (block, word, len)) in
+            writes
+                .iter()
+                .zip([(0, 0, 32), (1, 0, 32), (2, 0, 4), (2, 1, 4)])
+        {
+            let address = u16::from(&Address::data(9, block, word).expect("address"));
+            let pos = block as usize * 32 + word as usize * 4;
+            assert_eq!(if len == 32 { 0x82 } else { 0x02 }, frame[3]);
+            assert_eq!(&address.to_be_bytes(), &frame[4..6]);
+            assert_eq!(&slot_bytes[pos..pos + len], &frame[6..6 + len]);
+        }
+        assert!(ecc.write_pubkey(7, &key).is_err());
+    }
+
+    #[test]
+    fn write_config_round_trip() {
+        let bytes = crate::templates::tls_client_cert().to_bytes();
+        let mut responses: Vec<_> = bytes.chunks(32).map(MockTransport::frame).collect();
+        responses.extend(vec![Some(SUCCESS.to_vec()); 13]);
+        let mut ecc = mock_ecc(responses);
+        let config = ecc.read_config().expect("read config");
+        ecc.write_config(&config).expect("write config");
+
+        // block 0 from the I2C address on, block 1, block 2 without the
+        // user extra and lock bytes in word 5 and block 3
+        let mut writes = vec![];
+        writes.extend((4..8).map(|word| (0, word, 4)));
+        writes.push((1, 0, 32));
+        writes.extend((0..8).filter(|word| *word != 5).map(|word| (2, word, 4)));
+        writes.push((3, 0, 32));
+        let sent = &ecc.transport.sent[4..];
+        assert_eq!(writes.len(), sent.len());
+        for (frame, (block, word, len)) in sent.iter().zip(writes) {
+            let address = u16::from(&Address::config(block, word).expect("address"));
+            let pos = block as usize * 32 + word as usize * 4;
+            assert_eq!(if len == 32 { 0x80 } else { 0x00 }, frame[3]);
+            assert_eq!(&address.to_be_bytes(), &frame[4..6]);
+            assert_eq!(&bytes[pos..pos + len], &frame[6..6 + len]);
+        }
+    }
+
+    #[test]
+    fn read_slot() {
+        let data: Vec<u8> = (0..416).map(|n| n as u8).collect();
+        let mut responses = vec![Some(SUCCESS.to_vec()); 13];
+        responses.extend(data.chunks(32).map(MockTransport::frame));
+        let mut ecc = mock_ecc(responses);
+        ecc.write_bytes(8, 0, &data).expect("write slot");
+        assert_eq!(&data[..], &ecc.read_slot(8).expect("read slot")[..]);
+        assert_eq!(26, mock_attempts(&ecc));
+    }
+
+    #[test]
+    #[cfg(feature = "p256")]
+    fn verify_local() {
+        use p256::ecdsa::{signature::hazmat::PrehashSigner, SigningKey};
+
+        let signing_key = SigningKey::from_slice(&[0x01; 32]).expect("signing key");
+        let point = signing_key.verifying_key().to_encoded_point(false);
+        let public_key = PublicKey::try_from(&point.as_bytes()[1..]).expect("public key");
+        let message = b"hello world";
+        let signature: p256::ecdsa::Signature = signing_key
+            .sign_prehash(&Sha256::digest(message))
+            .expect("signature");
+        let signature = signature.to_bytes();
+
+        assert!(Ecc::verify_local(&public_key, message, &signature).expect("verify"));
+        assert!(!Ecc::verify_local(&public_key, b"hello there", &signature).expect("verify"));
+
+        // malformed inputs are errors rather than failed verifications
+        assert!(matches!(
+            Ecc::verify_local(&public_key, message, &signature[..63]),
+            Err(Error::InvalidLength { .. })
+        ));
+        assert!(matches!(
+            Ecc::verify_local(&public_key, message, &[0; 64]),
+            Err(Error::InvalidSignature)
+        ));
+        let off_curve = PublicKey {
+            x: [1; 32],
+            y: [2; 32],
+        };
+        assert!(matches!(
+            Ecc::verify_local(&off_curve, message, &signature),
+            Err(Error::InvalidPublicKey)
+        ));
+    }
+}
@@ -1,29 +1,169 @@
 use thiserror::Error;
 
+/// The errors of this crate. Besides the variants themselves,
+/// [`is_retryable`](Self::is_retryable) tells transient bus and chip
+/// failures apart from bad arguments and hard faults.
 #[derive(Error, Debug)]
 pub enum Error {
-    #[error("io error")]
-    IoError(#[from] std::io::Error),
+    /// An I/O error not covered by the bus errors below
+    #[error("i/o error")]
+    Io(#[from] std::io::Error),
+    /// The command didn't succeed within the configured retries
     #[error("timeout/retry error")]
     Timeout,
-    #[error("ecc error {:?}", .0)]
-    Ecc(crate::command::EccError),
+    /// A response failed its CRC check
+    #[error("crc error")]
+    Crc,
+    /// The chip reported an error status for the command with the given
+    /// opcode
+    #[error("ecc command {opcode:#04x} failed")]
+    Ecc {
+        opcode: u8,
+        #[source]
+        error: crate::command::EccError,
+    },
     #[error("serial port error")]
     SerialPort(#[from] serialport::Error),
+    #[error("no device responding")]
+    NoDevice,
+    #[error("i2c bus error")]
+    BusError(#[source] std::io::Error),
+    #[error("i2c bus busy")]
+    BusBusy,
+    // the errors below are raised on the host, for bad arguments before any
+    // command is sent and for data that doesn't parse or check out
     #[error("invalid ecc address")]
     InvalidAddress,
+    #[error("invalid slot {0}")]
+    InvalidSlot(u8),
+    #[error("invalid slot {0} configuration: {1}")]
+    InvalidSlotConfig(u8, &'static str),
+    #[error("invalid counter {0}")]
+    InvalidCounter(u8),
+    /// The monotonic counter is at its maximum value and can't be
+    /// incremented again
+    #[error("counter {0} exhausted")]
+    CounterExhausted(u8),
+    #[error("invalid length, expected {expected} bytes, got {got}")]
+    InvalidLength { expected: usize, got: usize },
+    #[error("authentication failed")]
+    AuthenticationFailed,
+    #[error("invalid signature encoding")]
+    InvalidSignature,
+    #[error("invalid public key")]
+    InvalidPublicKey,
+    #[error("invalid serial number")]
+    InvalidSerialNumber,
+    /// The locked config zone differs from the config to provision
+    #[error("locked config doesn't match, {} differences", .0.len())]
+    ConfigMismatch(Vec<crate::ConfigDiff>),
+    #[error("invalid compressed certificate: {0}")]
+    InvalidCompressedCert(&'static str),
+    #[error("invalid config, {} problems", .0.len())]
+    InvalidConfig(Vec<crate::ConfigError>),
+    /// The command sent to a `ReplayTransport` isn't the next recorded
+    /// command, `index` counts the exchanges replayed before it
+    #[error("command {index} doesn't match the recording")]
+    ReplayMismatch { index: usize },
+    #[cfg(feature = "x509")]
+    #[error("x509 encoding error")]
+    X509(#[source] x509_cert::der::Error),
 }
 
 impl Error {
+    /// Whether the failed command is worth sending again. Missing devices and
+    /// bus faults are not, they need the bus or the device looked at.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::Timeout | Self::Crc | Self::BusBusy | Self::Io(_) => true,
+            Self::Ecc { error, .. } => error.is_recoverable(),
+            _ => false,
+        }
+    }
+
     pub(crate) fn timeout() -> Self {
         Self::Timeout
     }
 
-    pub(crate) fn ecc(err: crate::command::EccError) -> Self {
-        Self::Ecc(err)
+    pub(crate) fn crc() -> Self {
+        Self::Crc
+    }
+
+    pub(crate) fn ecc(opcode: u8, error: crate::command::EccError) -> Self {
+        Self::Ecc { opcode, error }
+    }
+
+    pub(crate) fn no_device() -> Self {
+        Self::NoDevice
+    }
+
+    pub(crate) fn bus_error(err: std::io::Error) -> Self {
+        Self::BusError(err)
+    }
+
+    pub(crate) fn bus_busy() -> Self {
+        Self::BusBusy
     }
 
     pub(crate) fn invalid_address() -> Self {
         Self::InvalidAddress
     }
+
+    pub(crate) fn invalid_slot(slot: u8) -> Self {
+        Self::InvalidSlot(slot)
+    }
+
+    pub(crate) fn invalid_slot_config(slot: u8, reason: &'static str) -> Self {
+        Self::InvalidSlotConfig(slot, reason)
+    }
+
+    pub(crate) fn invalid_counter(counter_id: u8) -> Self {
+        Self::InvalidCounter(counter_id)
+    }
+
+    pub(crate) fn replay_mismatch(index: usize) -> Self {
+        Self::ReplayMismatch { index }
+    }
+
+    pub(crate) fn counter_exhausted(counter_id: u8) -> Self {
+        Self::CounterExhausted(counter_id)
+    }
+
+    pub(crate) fn invalid_length(expected: usize, got: usize) -> Self {
+        Self::InvalidLength { expected, got }
+    }
+
+    pub(crate) fn authentication_failed() -> Self {
+        Self::AuthenticationFailed
+    }
+
+    pub(crate) fn invalid_signature() -> Self {
+        Self::InvalidSignature
+    }
+
+    #[cfg(feature = "p256")]
+    pub(crate) fn invalid_public_key() -> Self {
+        Self::InvalidPublicKey
+    }
+
+    pub(crate) fn invalid_serial_number() -> Self {
+        Self::InvalidSerialNumber
+    }
+
+    pub(crate) fn config_mismatch(diffs: Vec<crate::ConfigDiff>) -> Self {
+        Self::ConfigMismatch(diffs)
+    }
+
+    pub(crate) fn invalid_compressed_cert(reason: &'static str) -> Self {
+        Self::InvalidCompressedCert(reason)
+    }
+
+    pub(crate) fn invalid_config(errors: Vec<crate::ConfigError>) -> Self {
+        Self::InvalidConfig(errors)
+    }
+
+    #[cfg(feature = "x509")]
+    pub(crate) fn x509(err: x509_cert::der::Error) -> Self {
+        Self::X509(err)
+    }
 }
@@ -0,0 +1,100 @@
+//! A [`Transport`] on top of an [`embedded_hal::i2c::I2c`] bus, for hosts that
+//! don't talk to the ECC through Linux device files.
+
+use crate::{
+    constants::{ATCA_I2C_COMMAND_FLAG, ATCA_RSP_SIZE_MAX},
+    transport::Transport,
+    Error, Result,
+};
+use bytes::BytesMut;
+use embedded_hal::{delay::DelayNs, i2c::I2c};
+use std::{io, time::Duration};
+
+const RECV_RETRY_WAIT: Duration = Duration::from_millis(50);
+const RECV_RETRIES: u8 = 2;
+
+pub struct HalTransport<I, D> {
+    i2c: I,
+    delay: D,
+    address: u8,
+}
+
+impl<I: I2c, D: DelayNs> HalTransport<I, D> {
+    /// Creates a transport for the ECC at the given 7 bit address. Command
+    /// and wake delays are waited out with `delay`.
+    pub fn new(i2c: I, delay: D, address: u8) -> Self {
+        Self {
+            i2c,
+            delay,
+            address,
+        }
+    }
+
+    /// Returns the bus and the delay
+    pub fn release(self) -> (I, D) {
+        (self.i2c, self.delay)
+    }
+
+    fn delay(&mut self, duration: Duration) {
+        self.delay
+            .delay_us(duration.as_micros().min(u32::MAX as u128) as u32);
+    }
+}
+
+impl<I: I2c, D: DelayNs> Transport for HalTransport<I, D> {
+    fn send_wake(&mut self, wake_delay: Duration) -> Result {
+        // holding sda low for the wake pulse is done by addressing 0x00,
+        // which no device acknowledges
+        let _ = self.i2c.write(0x00, &[0]);
+        self.delay(wake_delay);
+        // an already awake chip answers with its output buffer instead of the
+        // wake response, only a read that isn't acknowledged means no device
+        let mut buf = [0u8; 4];
+        self.i2c
+            .read(self.address, &mut buf)
+            .map_err(|_| Error::no_device())
+    }
+
+    fn send_sleep(&mut self) {
+        let _ = self.i2c.write(self.address, &[1]);
+    }
+
+    fn send_idle(&mut self) -> Result {
+        self.i2c.write(self.address, &[2]).map_err(i2c_error)
+    }
+
+    fn send_recv_buf(&mut self, delay: Duration, buf: &mut BytesMut) -> Result {
+        self.i2c.write(self.address, &buf[..]).map_err(i2c_error)?;
+        self.delay(delay);
+        buf.resize(ATCA_RSP_SIZE_MAX as usize, 0);
+        buf[0] = 0xff;
+        for _retry in 0..RECV_RETRIES {
+            if self.i2c.read(self.address, &mut buf[..]).is_ok() {
+                break;
+            }
+            self.delay(RECV_RETRY_WAIT);
+        }
+        let count = buf[0] as usize;
+        if count == 0xff {
+            return Err(Error::timeout());
+        }
+        buf.truncate(count);
+        Ok(())
+    }
+
+    fn put_command_flag(&self) -> u8 {
+        ATCA_I2C_COMMAND_FLAG
+    }
+
+    fn set_address(&mut self, address: u16) -> Result {
+        if address > 0x7F {
+            return Err(Error::invalid_address());
+        }
+        self.address = address as u8;
+        Ok(())
+    }
+}
+
+fn i2c_error<E: embedded_hal::i2c::Error>(err: E) -> Error {
+    Error::Io(io::Error::other(format!("i2c error: {:?}", err.kind())))
+}
@@ -0,0 +1,141 @@
+//! Host side computations of the digests the chip calculates internally
+//! during authorized and encrypted commands. These mirror the message layouts
+//! described in the data sheet for each command.
+
+use crate::constants::{
+    ATCA_GENDIG, ATCA_KEY_SIZE, ATCA_MAC, ATCA_NONCE, ATCA_PRIVWRITE, ATCA_WRITE,
+};
+use crate::secret::Secret;
+use sha2::{Digest, Sha256};
+use std::{fs::File, io::Read};
+
+pub(crate) type Key = [u8; ATCA_KEY_SIZE];
+
+/// TempKey after a random mode Nonce with the given chip random output and
+/// host input number.
+pub(crate) fn nonce_digest(rand_out: &[u8], num_in: &[u8]) -> Key {
+    let mut sha = Sha256::new();
+    sha.update(rand_out);
+    sha.update(num_in);
+    sha.update([ATCA_NONCE, 0x00, 0x00]);
+    sha.finalize().into()
+}
+
+/// TempKey after a GenDig of the given key. `serial` is the 9 byte chip
+/// serial number.
+pub(crate) fn gen_dig_digest(
+    key: &[u8],
+    zone: u8,
+    key_slot: u8,
+    serial: &[u8],
+    temp_key: &[u8],
+) -> Key {
+    let mut sha = Sha256::new();
+    sha.update(key);
+    sha.update([ATCA_GENDIG, zone, key_slot, 0x00]);
+    sha.update([serial[8], serial[0], serial[1]]);
+    sha.update([0u8; 25]);
+    sha.update(temp_key);
+    sha.finalize().into()
+}
+
+/// Response of a MAC command with the given mode byte over the given key and
+/// challenge. Only modes that don't include OTP or extended serial number
+/// bits are supported.
+pub(crate) fn mac_digest(
+    key: &[u8],
+    challenge: &[u8],
+    mode: u8,
+    key_slot: u8,
+    serial: &[u8],
+) -> Key {
+    let mut sha = Sha256::new();
+    sha.update(key);
+    sha.update(challenge);
+    sha.update([ATCA_MAC, mode, key_slot, 0x00]);
+    sha.update([0u8; 11]);
+    sha.update([serial[8]]);
+    sha.update([0u8; 4]);
+    sha.update([serial[0], serial[1]]);
+    sha.update([0u8; 2]);
+    sha.finalize().into()
+}
+
+/// Input MAC for a PrivWrite of the given 36 byte padded private key.
+pub(crate) fn priv_write_mac(temp_key: &[u8], key_slot: u8, serial: &[u8], key: &[u8]) -> Key {
+    let mut sha = Sha256::new();
+    sha.update(temp_key);
+    sha.update([ATCA_PRIVWRITE, 0x40, key_slot, 0x00]);
+    sha.update([serial[8], serial[0], serial[1]]);
+    sha.update([0u8; 21]);
+    sha.update(key);
+    sha.finalize().into()
+}
+
+/// Input MAC for an encrypted Write of the given 32 byte cleartext data.
+/// `address` is the Write param2 in the order it is sent to the chip.
+pub(crate) fn write_mac(
+    temp_key: &[u8],
+    param1: u8,
+    address: [u8; 2],
+    serial: &[u8],
+    data: &[u8],
+) -> Key {
+    let mut sha = Sha256::new();
+    sha.update(temp_key);
+    sha.update([ATCA_WRITE, param1, address[0], address[1]]);
+    sha.update([serial[8], serial[0], serial[1]]);
+    sha.update([0u8; 25]);
+    sha.update(data);
+    sha.finalize().into()
+}
+
+/// Encrypts data for a PrivWrite or encrypted Write by XORing it with the
+/// session key. Data past the first 32 bytes is XORed with the digest of the
+/// session key.
+pub(crate) fn encrypt(session_key: &[u8], data: &[u8]) -> Vec<u8> {
+    let pad = Secret::<Key>(Sha256::digest(session_key).into());
+    data.iter()
+        .zip(session_key.iter().chain(pad.iter()))
+        .map(|(d, k)| d ^ k)
+        .collect()
+}
+
+/// A fresh 32 byte challenge from the host's random source, which is
+/// independent of the chip being challenged.
+pub(crate) fn random_challenge() -> std::io::Result<Key> {
+    let mut challenge = [0u8; ATCA_KEY_SIZE];
+    File::open("/dev/urandom")?.read_exact(&mut challenge)?;
+    Ok(challenge)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mac() {
+        let serial = [0x01, 0x23, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0xEE];
+        let digest = mac_digest(&[0x01; 32], &[0x02; 32], 0x00, 3, &serial);
+        assert_eq!(
+            [
+                0x93, 0x9d, 0x72, 0x11, 0x75, 0xb2, 0xf2, 0x51, 0x06, 0xa2, 0xd8, 0x0e, 0xde, 0x6b,
+                0xd9, 0xa0, 0x7e, 0xdb, 0xf0, 0x05, 0xf0, 0x58, 0xa6, 0x8a, 0x87, 0x48, 0x49, 0x5d,
+                0x35, 0xe7, 0xb7, 0x44
+            ],
+            digest
+        );
+    }
+
+    #[test]
+    fn encrypt_padded_key() {
+        let session_key = [0x5a; ATCA_KEY_SIZE];
+        let data = [0x11; 36];
+        let encrypted = encrypt(&session_key, &data);
+        assert_eq!(36, encrypted.len());
+        assert_eq!(0x11 ^ 0x5a, encrypted[0]);
+        let pad = Sha256::digest(session_key);
+        assert_eq!(0x11 ^ pad[0], encrypted[32]);
+        assert_eq!(&data[..], &encrypt(&session_key, &encrypted)[..]);
+    }
+}
@@ -0,0 +1,95 @@
+use bytes::Buf;
+use serde_derive::Serialize;
+
+/// The volatile device state as reported by the Info command in State mode.
+#[derive(Serialize, Debug, PartialEq, Eq, Clone, Copy)]
+pub struct ChipState {
+    /// TempKey holds a valid value
+    pub tempkey_valid: bool,
+    /// The slot TempKey was derived from, if it was derived from a slot
+    pub tempkey_key_id: u8,
+    /// TempKey was generated from a random nonce rather than a fixed input
+    pub tempkey_source_random: bool,
+    /// TempKey was generated by GenDig
+    pub tempkey_gendig_data: bool,
+    /// TempKey was generated by GenKey
+    pub tempkey_genkey_data: bool,
+    /// TempKey was generated by a command that does not allow it to be used
+    /// with MAC
+    pub tempkey_no_mac: bool,
+    /// The EEPROM random seed was updated since power up
+    pub eeprom_rng: bool,
+    /// The SRAM random seed is valid
+    pub sram_rng: bool,
+    /// An authorization is in effect
+    pub auth_valid: bool,
+    /// The slot used for the current authorization
+    pub auth_key: u8,
+}
+
+impl From<u16> for ChipState {
+    fn from(v: u16) -> Self {
+        let bit = |n: u16| v & (1 << n) != 0;
+        Self {
+            tempkey_key_id: (v & 0x0F) as u8,
+            tempkey_source_random: !bit(4),
+            tempkey_gendig_data: bit(5),
+            tempkey_genkey_data: bit(6),
+            tempkey_no_mac: bit(7),
+            eeprom_rng: bit(8),
+            sram_rng: bit(9),
+            auth_valid: bit(10),
+            auth_key: ((v >> 11) & 0x0F) as u8,
+            tempkey_valid: bit(15),
+        }
+    }
+}
+
+impl From<&[u8]> for ChipState {
+    fn from(mut buf: &[u8]) -> Self {
+        buf.get_u16_le().into()
+    }
+}
+
+/// The part number of the chip, decoded from the Info revision.
+#[derive(Serialize, Debug, PartialEq, Eq, Clone, Copy)]
+pub enum DeviceType {
+    Ecc608A,
+    Ecc608B,
+    /// Any other revision, like an ECC508A or a future 608 silicon revision
+    Unknown([u8; 4]),
+}
+
+impl From<[u8; 4]> for DeviceType {
+    /// Maps the revision returned by [`Ecc::info_revision`](crate::Ecc::info_revision).
+    /// The third byte is `0x60` for all 608 parts and the last byte is the
+    /// silicon revision: up to `0x02` for the 608A and `0x03` for the 608B.
+    fn from(revision: [u8; 4]) -> Self {
+        match revision {
+            [0x00, 0x00, 0x60, 0x01..=0x02] => Self::Ecc608A,
+            [0x00, 0x00, 0x60, 0x03] => Self::Ecc608B,
+            _ => Self::Unknown(revision),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn device_type() {
+        assert_eq!(
+            DeviceType::Ecc608A,
+            DeviceType::from([0x00, 0x00, 0x60, 0x02])
+        );
+        assert_eq!(
+            DeviceType::Ecc608B,
+            DeviceType::from([0x00, 0x00, 0x60, 0x03])
+        );
+        assert_eq!(
+            DeviceType::Unknown([0x00, 0x00, 0x50, 0x00]),
+            DeviceType::from([0x00, 0x00, 0x50, 0x00])
+        );
+    }
+}
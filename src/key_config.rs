@@ -1,18 +1,25 @@
+use crate::{config_zone::flags, Error};
 use bitfield::bitfield;
 use bytes::Buf;
+#[cfg(feature = "serde")]
+use serde_derive::Deserialize;
 use serde_derive::Serialize;
+use std::fmt;
 
 #[derive(Serialize, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize))]
 #[serde(rename_all = "lowercase")]
 pub enum KeyConfigType {
     Ecc,
+    Aes,
     NotEcc,
 }
 
 impl From<u8> for KeyConfigType {
     fn from(v: u8) -> Self {
-        match v & 4 == 4 {
-            true => Self::Ecc,
+        match v {
+            4 => Self::Ecc,
+            6 => Self::Aes,
             _ => Self::NotEcc,
         }
     }
@@ -22,6 +29,7 @@ impl From<KeyConfigType> for u8 {
     fn from(v: KeyConfigType) -> Self {
         match v {
             KeyConfigType::Ecc => 4,
+            KeyConfigType::Aes => 6,
             KeyConfigType::NotEcc => 7,
         }
     }
@@ -35,9 +43,8 @@ impl From<&[u8]> for KeyConfig {
 }
 
 bitfield! {
-    #[derive(PartialEq)]
+    #[derive(PartialEq, Clone, Copy)]
     pub struct KeyConfig(u16);
-    impl Debug;
 
     pub u8, auth_key, set_auth_key: 3, 0;
     pub intrusion_disable, set_intrusion_disable: 4;
@@ -69,6 +76,84 @@ impl From<&KeyConfig> for u16 {
     }
 }
 
+impl KeyConfig {
+    /// The ECC608 name of the bit the ECC508 calls IntrusionDisable. When set
+    /// the key can only be used while the persistent latch is set.
+    pub fn persistent_disable(&self) -> bool {
+        self.intrusion_disable()
+    }
+
+    pub fn with_private(mut self, private: bool) -> Self {
+        self.set_private(private);
+        self
+    }
+
+    pub fn with_pub_info(mut self, pub_info: bool) -> Self {
+        self.set_pub_info(pub_info);
+        self
+    }
+
+    pub fn with_key_type(mut self, key_type: KeyConfigType) -> Self {
+        self.set_key_type(key_type);
+        self
+    }
+
+    pub fn with_lockable(mut self, lockable: bool) -> Self {
+        self.set_is_lockable(lockable);
+        self
+    }
+
+    pub fn with_req_random(mut self, req_random: bool) -> Self {
+        self.set_req_random(req_random);
+        self
+    }
+
+    pub fn with_req_auth(mut self, req_auth: bool) -> Self {
+        self.set_req_auth(req_auth);
+        self
+    }
+
+    pub fn with_auth_key(mut self, auth_key: u8) -> Self {
+        self.set_auth_key(auth_key);
+        self
+    }
+
+    pub fn with_persistent_disable(mut self, persistent_disable: bool) -> Self {
+        self.set_intrusion_disable(persistent_disable);
+        self
+    }
+
+    pub fn with_x509_index(mut self, x509_index: u8) -> Self {
+        self.set_x509_index(x509_index);
+        self
+    }
+
+    /// Checks the config of the given slot for combinations that can't work,
+    /// which would leave the slot unusable once the config zone is locked.
+    pub fn validate(&self, slot: u8) -> crate::Result {
+        let key_type = self.key_type();
+        if self.private() && key_type != KeyConfigType::Ecc {
+            return Err(Error::invalid_slot_config(
+                slot,
+                "private keys must be ECC keys",
+            ));
+        }
+        if self.pub_info() && key_type == KeyConfigType::Aes {
+            return Err(Error::invalid_slot_config(
+                slot,
+                "AES keys have no public info",
+            ));
+        }
+        if self.req_auth() && self.auth_key() == slot {
+            return Err(Error::invalid_slot_config(
+                slot,
+                "a key can't authorize its own use",
+            ));
+        }
+        Ok(())
+    }
+}
+
 ///  Returns a key configuration set up to store ECC key private keys.
 impl Default for KeyConfig {
     fn default() -> Self {
@@ -81,6 +166,46 @@ impl Default for KeyConfig {
     }
 }
 
+impl fmt::Debug for KeyConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KeyConfig")
+            .field("auth_key", &self.auth_key())
+            .field("persistent_disable", &self.persistent_disable())
+            .field("x509_index", &self.x509_index())
+            .field("private", &self.private())
+            .field("pub_info", &self.pub_info())
+            .field("key_type", &self.key_type())
+            .field("lockable", &self.lockable())
+            .field("req_random", &self.req_random())
+            .field("req_auth", &self.req_auth())
+            .finish()
+    }
+}
+
+/// Prints the raw value followed by the key type, the set flags and the
+/// index fields, e.g. `0x3300 key_type=Ecc private pub_info lockable
+/// auth_key=0 x509_index=0`.
+impl fmt::Display for KeyConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:#06x} key_type={:?}{} auth_key={} x509_index={}",
+            self.0,
+            self.key_type(),
+            flags(&[
+                ("private", self.private()),
+                ("pub_info", self.pub_info()),
+                ("lockable", self.lockable()),
+                ("req_random", self.req_random()),
+                ("req_auth", self.req_auth()),
+                ("persistent_disable", self.persistent_disable()),
+            ]),
+            self.auth_key(),
+            self.x509_index(),
+        )
+    }
+}
+
 impl serde::ser::Serialize for KeyConfig {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -101,3 +226,72 @@ impl serde::ser::Serialize for KeyConfig {
         state.end()
     }
 }
+
+/// The named fields of a serialized [`KeyConfig`].
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+struct KeyConfigFields {
+    auth_key: u8,
+    intrusion_disable: bool,
+    x509_index: u8,
+    private: bool,
+    pub_info: bool,
+    key_type: KeyConfigType,
+    lockable: bool,
+    req_random: bool,
+    req_auth: bool,
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::de::Deserialize<'de> for KeyConfig {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::de::Deserializer<'de>,
+    {
+        let fields = KeyConfigFields::deserialize(deserializer)?;
+        Ok(KeyConfig::from(0)
+            .with_auth_key(fields.auth_key)
+            .with_persistent_disable(fields.intrusion_disable)
+            .with_x509_index(fields.x509_index)
+            .with_private(fields.private)
+            .with_pub_info(fields.pub_info)
+            .with_key_type(fields.key_type)
+            .with_lockable(fields.lockable)
+            .with_req_random(fields.req_random)
+            .with_req_auth(fields.req_auth))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn with_fields() {
+        // an ECC private key slot and a lockable public key slot
+        assert_eq!(0x3300, u16::from(KeyConfig::default()));
+        let public = KeyConfig::from(0)
+            .with_key_type(KeyConfigType::Ecc)
+            .with_lockable(true);
+        assert_eq!(0x3000, u16::from(&public));
+        // a slot 0 private key that needs an authorization by slot 3
+        let auth = KeyConfig::default().with_req_auth(true).with_auth_key(3);
+        assert_eq!(0xB303, u16::from(&auth));
+        assert!(auth.validate(0).is_ok());
+        assert!(auth.validate(3).is_err());
+        assert!(KeyConfig::default()
+            .with_key_type(KeyConfigType::Aes)
+            .validate(0)
+            .is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn serde_round_trip() {
+        let config = KeyConfig::default().with_req_auth(true).with_auth_key(3);
+        let json = serde_json::to_string(&config).expect("serialize");
+        assert!(json.contains("\"key_type\":\"ecc\""));
+        let parsed: KeyConfig = serde_json::from_str(&json).expect("deserialize");
+        assert_eq!(config, parsed);
+    }
+}
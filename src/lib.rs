@@ -1,16 +1,74 @@
+/// Emits a tracing event when the `tracing` feature is enabled and compiles
+/// to nothing otherwise.
+macro_rules! trace_event {
+    ($level:ident, $($arg:tt)*) => {
+        #[cfg(feature = "tracing")]
+        tracing::$level!($($arg)*);
+    };
+}
+
+#[cfg(feature = "async")]
+mod async_ecc;
 mod command;
 mod constants;
+#[cfg(feature = "x509")]
+mod csr;
 mod error;
+#[cfg(feature = "embedded-hal")]
+mod hal_transport;
+mod host;
+#[cfg(feature = "rcgen")]
+mod rcgen_key_pair;
+mod recording;
+#[cfg(feature = "rustls")]
+mod rustls_signing_key;
+mod secret;
+mod shared_ecc;
 mod transport;
 
 pub mod address;
+pub mod compressed_cert;
+pub mod config_zone;
 pub mod ecc;
+pub mod info;
 pub mod key_config;
+pub mod provision;
+pub mod public_key;
+pub mod serial_number;
+pub mod signature;
 pub mod slot_config;
+pub mod templates;
 
 pub use error::Error;
+#[cfg(feature = "embedded-hal")]
+pub use hal_transport::HalTransport;
+#[cfg(feature = "rcgen")]
+pub use rcgen_key_pair::EccKeyPair;
+#[cfg(feature = "rustls")]
+pub use rustls_signing_key::EccSigningKey;
 pub type Result<T = ()> = std::result::Result<T, Error>;
 pub use address::*;
-pub use ecc::{Ecc, KeyType, MAX_SLOT};
+#[cfg(feature = "async")]
+pub use async_ecc::AsyncEcc;
+pub use command::{
+    AesMode, EccCommand, EccError, InfoMode, KdfAlgorithm, NonceMode, SecureBootMode, ShaMode,
+};
+pub use compressed_cert::*;
+pub use config_zone::*;
+pub use ecc::{
+    slot_size, DeriveKeyMode, Ecc, EccBuilder, KdfSource, KdfTarget, KeyType, LatencyStats,
+    MacMode, SelfTestFlags, SelfTestResult, Session, UpdateExtraTarget, MAX_COUNTER,
+    MAX_COUNTER_VALUE, MAX_SLOT,
+};
+pub use info::*;
 pub use key_config::*;
+pub use provision::*;
+pub use public_key::*;
+pub use recording::{RecordingTransport, ReplayTransport};
+pub use serial_number::*;
+pub use shared_ecc::SharedEcc;
+pub use signature::*;
 pub use slot_config::*;
+#[cfg(feature = "test-util")]
+pub use transport::MockTransport;
+pub use transport::{Transport, TransportProtocol};
@@ -0,0 +1,205 @@
+//! Provisioning a device from a config and slot contents in the one order
+//! that works: write and lock the config, fill the slots, lock the data.
+
+use crate::{
+    transport::Transport, ConfigDiff, ConfigZone, Ecc, Error, KeyConfigType, KeyType, PublicKey,
+    Result, Zone,
+};
+
+/// What a slot is provisioned with
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum SlotContent {
+    /// A P256 private key generated on the chip with GenKey
+    PrivateKey,
+    /// A public key in the padded 72 byte layout, slots 8 to 15 only
+    PublicKey(PublicKey),
+    /// Clear text data written from the start of the slot
+    Data(Vec<u8>),
+}
+
+/// Runs the provisioning sequence, see [`run`](Self::run):
+///
+/// ```no_run
+/// # fn main() -> ecc608_linux::Result {
+/// use ecc608_linux::{templates, Ecc, Provisioner};
+///
+/// let mut ecc = Ecc::from_path("/dev/i2c-1", 0x60)?;
+/// let public_keys = Provisioner::new(templates::tls_client_cert())
+///     .private_key(0)
+///     .data(8, vec![0; 32])
+///     .run(&mut ecc)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct Provisioner {
+    config: ConfigZone,
+    slots: Vec<(u8, SlotContent)>,
+}
+
+/// Fields that change in use or through the lock commands, and so aren't
+/// compared against a locked config
+const RUNTIME_FIELDS: [&str; 7] = [
+    "counter0",
+    "counter1",
+    "user_extra",
+    "user_extra_add",
+    "lock_value",
+    "lock_config",
+    "slot_locked",
+];
+
+impl Provisioner {
+    pub fn new(config: ConfigZone) -> Self {
+        Self {
+            config,
+            slots: vec![],
+        }
+    }
+
+    /// Generates a private key in the slot.
+    pub fn private_key(self, slot: u8) -> Self {
+        self.slot(slot, SlotContent::PrivateKey)
+    }
+
+    /// Writes a public key to the slot.
+    pub fn public_key(self, slot: u8, key: PublicKey) -> Self {
+        self.slot(slot, SlotContent::PublicKey(key))
+    }
+
+    /// Writes data to the slot, like a compressed certificate.
+    pub fn data(self, slot: u8, data: Vec<u8>) -> Self {
+        self.slot(slot, SlotContent::Data(data))
+    }
+
+    pub fn slot(mut self, slot: u8, content: SlotContent) -> Self {
+        self.slots.push((slot, content));
+        self
+    }
+
+    /// Provisions the device and returns the public keys of the private key
+    /// slots, in the order they were added.
+    ///
+    /// Runs the steps that aren't done yet, judged by the lock state:
+    ///
+    /// 1. validate and write the config, then lock the config zone. A
+    ///    config zone that is already locked has to match the config, else
+    ///    the run stops with [`Error::ConfigMismatch`].
+    /// 2. generate the private keys and write the public keys and data,
+    ///    then lock the data zone. All of this is skipped once the data zone
+    ///    is locked.
+    ///
+    /// Any failure stops the run, and since a step only counts as done once
+    /// its zone is locked, running again after a failure picks up at the
+    /// step that failed. Private keys are generated again in that case.
+    pub fn run<T: Transport>(&self, ecc: &mut Ecc<T>) -> Result<Vec<(u8, PublicKey)>> {
+        self.check_slots()?;
+        let actual = ecc.read_config()?;
+        if actual.config_locked() {
+            let diffs = self.mismatches(&actual);
+            if !diffs.is_empty() {
+                return Err(Error::config_mismatch(diffs));
+            }
+        } else {
+            self.config.validate().map_err(Error::invalid_config)?;
+            ecc.write_config(&self.config)?;
+            ecc.set_config_locked_validated()?;
+        }
+
+        if !actual.data_locked() {
+            for (slot, content) in &self.slots {
+                match content {
+                    SlotContent::PrivateKey => ecc.genkey(KeyType::Private, *slot).map(|_| ())?,
+                    SlotContent::PublicKey(key) => ecc.write_pubkey(*slot, key)?,
+                    SlotContent::Data(data) => ecc.write_bytes(*slot, 0, data)?,
+                }
+            }
+            ecc.set_locked(Zone::Data)?;
+        }
+
+        self.slots
+            .iter()
+            .filter(|(_, content)| *content == SlotContent::PrivateKey)
+            .map(|(slot, _)| Ok((*slot, ecc.genkey_public(*slot)?)))
+            .collect()
+    }
+
+    /// Catches slot contents the config can't take before anything is
+    /// written
+    fn check_slots(&self) -> Result {
+        for (slot, content) in &self.slots {
+            let key_config = self
+                .config
+                .key_configs
+                .get(*slot as usize)
+                .ok_or_else(|| Error::invalid_slot(*slot))?;
+            let is_private_key =
+                key_config.private() && key_config.key_type() == KeyConfigType::Ecc;
+            match content {
+                SlotContent::PrivateKey if !is_private_key => {
+                    return Err(Error::invalid_slot_config(
+                        *slot,
+                        "slot isn't configured for a private key",
+                    ))
+                }
+                SlotContent::PublicKey(_) | SlotContent::Data(_) if key_config.private() => {
+                    return Err(Error::invalid_slot_config(
+                        *slot,
+                        "slot is configured for a private key",
+                    ))
+                }
+                _ => (),
+            }
+        }
+        Ok(())
+    }
+
+    fn mismatches(&self, actual: &ConfigZone) -> Vec<ConfigDiff> {
+        self.config
+            .diff(actual)
+            .into_iter()
+            .filter(|diff| !RUNTIME_FIELDS.contains(&diff.field))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{templates, transport::MockTransport};
+
+    /// A mock that returns the config as locked, and then the public key of
+    /// slot 0. Any other command runs out of responses and fails.
+    fn locked_ecc(config: &ConfigZone) -> Ecc<MockTransport> {
+        let mut bytes = config.to_bytes();
+        // both zones and slot 0 locked
+        bytes[86..90].copy_from_slice(&[0x00, 0x00, 0xFE, 0xFF]);
+        let mut responses: std::collections::VecDeque<_> =
+            bytes.chunks(32).map(MockTransport::frame).collect();
+        responses.push_back(MockTransport::frame(&[0x42; 64]));
+        Ecc::new(MockTransport {
+            responses,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn resumes_locked_device() {
+        let config = templates::tls_client_cert();
+        let mut ecc = locked_ecc(&config);
+        let provisioner = Provisioner::new(config).private_key(0).data(8, vec![0; 32]);
+        let public_keys = provisioner.run(&mut ecc).expect("provisioned");
+        assert_eq!(0, public_keys[0].0);
+        assert_eq!([0x42; 32], public_keys[0].1.x);
+
+        let mut ecc = locked_ecc(&templates::secure_boot());
+        let err = provisioner.run(&mut ecc).expect_err("mismatch");
+        assert!(matches!(err, Error::ConfigMismatch(diffs) if !diffs.is_empty()));
+
+        let provisioner = Provisioner::new(templates::tls_client_cert()).private_key(8);
+        assert!(matches!(
+            provisioner.run(&mut locked_ecc(&templates::tls_client_cert())),
+            Err(Error::InvalidSlotConfig(8, _))
+        ));
+    }
+}
@@ -0,0 +1,171 @@
+use crate::{Error, Result};
+use std::convert::TryFrom;
+
+pub(crate) const PUBLIC_KEY_SLOT_SIZE: usize = 72;
+
+/// The DER SubjectPublicKeyInfo header for an uncompressed P256 point: the
+/// id-ecPublicKey and prime256v1 algorithm identifier followed by the bit
+/// string header.
+const SPKI_PREFIX: [u8; 26] = [
+    0x30, 0x59, 0x30, 0x13, 0x06, 0x07, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01, 0x06, 0x08, 0x2a,
+    0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07, 0x03, 0x42, 0x00,
+];
+
+/// A P256 public key point as returned by GenKey.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct PublicKey {
+    pub x: [u8; 32],
+    pub y: [u8; 32],
+}
+
+impl PublicKey {
+    /// Returns the SEC1 uncompressed encoding of the key: `0x04 || x || y`.
+    pub fn to_sec1_uncompressed(&self) -> [u8; 65] {
+        let mut result = [0u8; 65];
+        result[0] = 0x04;
+        result[1..33].copy_from_slice(&self.x);
+        result[33..].copy_from_slice(&self.y);
+        result
+    }
+
+    /// Returns the raw 64 byte `x || y` encoding the chip uses.
+    pub fn to_bytes(&self) -> [u8; 64] {
+        let mut result = [0u8; 64];
+        result[..32].copy_from_slice(&self.x);
+        result[32..].copy_from_slice(&self.y);
+        result
+    }
+
+    /// Returns the DER encoded SubjectPublicKeyInfo for the key, as written
+    /// by `openssl ec -pubout -outform DER`.
+    pub fn to_spki_der(&self) -> Vec<u8> {
+        let mut result = Vec::with_capacity(SPKI_PREFIX.len() + 65);
+        result.extend_from_slice(&SPKI_PREFIX);
+        result.extend_from_slice(&self.to_sec1_uncompressed());
+        result
+    }
+
+    /// Returns the SubjectPublicKeyInfo for the key as a `PUBLIC KEY` PEM
+    /// block.
+    pub fn to_spki_pem(&self) -> String {
+        let mut result = String::from("-----BEGIN PUBLIC KEY-----\n");
+        for (i, c) in base64(&self.to_spki_der()).chars().enumerate() {
+            if i > 0 && i % 64 == 0 {
+                result.push('\n');
+            }
+            result.push(c);
+        }
+        result.push_str("\n-----END PUBLIC KEY-----\n");
+        result
+    }
+
+    /// Converts the key to a p256 verifying key. Fails if the point is not on
+    /// the curve.
+    #[cfg(feature = "p256")]
+    pub fn to_p256(&self) -> Result<p256::ecdsa::VerifyingKey> {
+        p256::ecdsa::VerifyingKey::from_sec1_bytes(&self.to_sec1_uncompressed())
+            .map_err(|_| Error::invalid_public_key())
+    }
+
+    /// Returns the 72 byte layout used to store a public key in a data slot,
+    /// where both coordinates are preceded by 4 zero pad bytes.
+    pub(crate) fn to_slot_bytes(self) -> [u8; PUBLIC_KEY_SLOT_SIZE] {
+        let mut result = [0u8; PUBLIC_KEY_SLOT_SIZE];
+        result[4..36].copy_from_slice(&self.x);
+        result[40..].copy_from_slice(&self.y);
+        result
+    }
+
+    /// Parses the 72 byte data slot layout, ignoring the pad bytes.
+    pub(crate) fn from_slot_bytes(v: &[u8]) -> Result<Self> {
+        if v.len() != PUBLIC_KEY_SLOT_SIZE {
+            return Err(Error::invalid_length(PUBLIC_KEY_SLOT_SIZE, v.len()));
+        }
+        let mut x = [0u8; 32];
+        let mut y = [0u8; 32];
+        x.copy_from_slice(&v[4..36]);
+        y.copy_from_slice(&v[40..]);
+        Ok(Self { x, y })
+    }
+}
+
+impl TryFrom<&[u8]> for PublicKey {
+    type Error = Error;
+
+    /// Parses the raw 64 byte `x || y` encoding the chip uses.
+    fn try_from(v: &[u8]) -> Result<Self> {
+        if v.len() != 64 {
+            return Err(Error::invalid_length(64, v.len()));
+        }
+        let mut x = [0u8; 32];
+        let mut y = [0u8; 32];
+        x.copy_from_slice(&v[..32]);
+        y.copy_from_slice(&v[32..]);
+        Ok(Self { x, y })
+    }
+}
+
+/// Standard base64 with padding, enough for PEM output.
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut result = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let b = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let n = u32::from_be_bytes([0, b[0], b[1], b[2]]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                result.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                result.push('=');
+            }
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slot_layout_round_trip() {
+        let mut raw = [0u8; 64];
+        for (i, b) in raw.iter_mut().enumerate() {
+            *b = i as u8 + 1;
+        }
+        let key = PublicKey::try_from(&raw[..]).expect("public key");
+        let slot = key.to_slot_bytes();
+        assert_eq!(&[0, 0, 0, 0], &slot[0..4]);
+        assert_eq!(&raw[..32], &slot[4..36]);
+        assert_eq!(&[0, 0, 0, 0], &slot[36..40]);
+        assert_eq!(&raw[32..], &slot[40..]);
+        assert_eq!(key, PublicKey::from_slot_bytes(&slot).expect("slot key"));
+    }
+
+    /// The output of `openssl ec -pubout` for a generated prime256v1 key
+    const OPENSSL_SPKI_DER: [u8; 91] = [
+        0x30, 0x59, 0x30, 0x13, 0x06, 0x07, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01, 0x06, 0x08,
+        0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07, 0x03, 0x42, 0x00, 0x04, 0x9b, 0x85, 0x8e,
+        0xf7, 0xae, 0x3b, 0xcb, 0x2d, 0x19, 0x97, 0xf6, 0x8b, 0x99, 0x2a, 0x9b, 0x25, 0xfe, 0xb7,
+        0xc5, 0xad, 0xd1, 0xbd, 0x53, 0x70, 0x37, 0x9a, 0x47, 0x70, 0xca, 0x00, 0x42, 0x83, 0x2b,
+        0x03, 0xb5, 0x8c, 0x2c, 0xec, 0xb7, 0x03, 0xe5, 0x3c, 0x55, 0x20, 0x71, 0x51, 0xc3, 0xcc,
+        0x55, 0x27, 0xaa, 0x03, 0xb5, 0x75, 0xc7, 0xcd, 0x60, 0x86, 0xa3, 0x0c, 0xff, 0x87, 0xa0,
+        0x5c,
+    ];
+    const OPENSSL_SPKI_PEM: &str = "-----BEGIN PUBLIC KEY-----
+MFkwEwYHKoZIzj0CAQYIKoZIzj0DAQcDQgAEm4WO9647yy0Zl/aLmSqbJf63xa3R
+vVNwN5pHcMoAQoMrA7WMLOy3A+U8VSBxUcPMVSeqA7V1x81ghqMM/4egXA==
+-----END PUBLIC KEY-----
+";
+
+    #[test]
+    fn spki_matches_openssl() {
+        let key = PublicKey::try_from(&OPENSSL_SPKI_DER[27..]).expect("public key");
+        assert_eq!(&OPENSSL_SPKI_DER[..], &key.to_spki_der()[..]);
+        assert_eq!(OPENSSL_SPKI_PEM, key.to_spki_pem());
+    }
+}
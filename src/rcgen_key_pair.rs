@@ -0,0 +1,110 @@
+//! An [`rcgen`] remote key pair backed by a private key on the chip, to
+//! issue certificates and signing requests without exporting the key.
+
+use crate::{transport::Transport, Ecc, Result, Signature, TransportProtocol};
+use std::{
+    convert::TryFrom,
+    sync::{Arc, Mutex},
+};
+
+/// Signs with the P256 private key in a slot of a shared [`Ecc`]. Turn it
+/// into an rcgen key pair with [`into_key_pair`](Self::into_key_pair). This
+/// crate builds rcgen without a crypto backend, so certificates need an
+/// explicit serial number:
+///
+/// ```no_run
+/// # fn main() -> ecc608_linux::Result {
+/// use ecc608_linux::{Ecc, EccKeyPair};
+/// use std::sync::{Arc, Mutex};
+///
+/// let ecc = Arc::new(Mutex::new(Ecc::from_path("/dev/i2c-1", 0x60)?));
+/// let key_pair = EccKeyPair::new(ecc, 0)?.into_key_pair();
+/// let mut params = rcgen::CertificateParams::new(vec!["device".to_string()]).unwrap();
+/// params.serial_number = Some(rcgen::SerialNumber::from(vec![0x01]));
+/// let certificate = params.self_signed(&key_pair).unwrap();
+/// # Ok(())
+/// # }
+/// ```
+pub struct EccKeyPair<T = TransportProtocol> {
+    ecc: Arc<Mutex<Ecc<T>>>,
+    key_slot: u8,
+    public_key: [u8; 65],
+}
+
+impl<T: Transport> EccKeyPair<T> {
+    /// Reads the public key for the private key in the given slot.
+    pub fn new(ecc: Arc<Mutex<Ecc<T>>>, key_slot: u8) -> Result<Self> {
+        let public_key = ecc
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .genkey_public(key_slot)?
+            .to_sec1_uncompressed();
+        Ok(Self {
+            ecc,
+            key_slot,
+            public_key,
+        })
+    }
+}
+
+impl<T: Transport + Send + 'static> EccKeyPair<T> {
+    pub fn into_key_pair(self) -> rcgen::KeyPair {
+        // from_remote never fails, it only stores the remote key
+        rcgen::KeyPair::from_remote(Box::new(self)).expect("remote key pair")
+    }
+}
+
+impl<T: Transport> rcgen::RemoteKeyPair for EccKeyPair<T> {
+    fn public_key(&self) -> &[u8] {
+        &self.public_key
+    }
+
+    fn sign(&self, msg: &[u8]) -> std::result::Result<Vec<u8>, rcgen::Error> {
+        let mut ecc = self.ecc.lock().unwrap_or_else(|err| err.into_inner());
+        let signature = ecc
+            .sign(self.key_slot, msg)
+            .and_then(Signature::try_from)
+            .map_err(|_| rcgen::Error::RemoteKeyError)?;
+        Ok(signature.to_der())
+    }
+
+    fn algorithm(&self) -> &'static rcgen::SignatureAlgorithm {
+        &rcgen::PKCS_ECDSA_P256_SHA256
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::MockTransport;
+
+    #[test]
+    fn self_signed() {
+        let mut public_key = [0u8; 64];
+        public_key[0] = 0x42;
+        let mut signature = [0u8; 64];
+        signature[31] = 0x01;
+        signature[63] = 0x02;
+        let ecc = Ecc::new(MockTransport {
+            // GenKey, then Random, Nonce and Sign
+            responses: vec![
+                MockTransport::frame(&public_key),
+                MockTransport::frame(&[0; 32]),
+                MockTransport::frame(&[0]),
+                MockTransport::frame(&signature),
+            ]
+            .into(),
+            ..Default::default()
+        });
+        let key_pair = EccKeyPair::new(Arc::new(Mutex::new(ecc)), 0)
+            .expect("key pair")
+            .into_key_pair();
+        assert_eq!(65, key_pair.public_key_raw().len());
+        let mut params = rcgen::CertificateParams::new(vec!["device".to_string()]).expect("params");
+        params.serial_number = Some(rcgen::SerialNumber::from(vec![0x01]));
+        let certificate = params.self_signed(&key_pair).expect("certificate");
+        // the certificate ends with the chip's signature
+        let der = certificate.der();
+        assert!(der.ends_with(&Signature(signature).to_der()));
+    }
+}
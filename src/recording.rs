@@ -0,0 +1,262 @@
+//! Recording the command traffic of a real device and replaying it without
+//! one, to reproduce a field failure in a test.
+//!
+//! A recording is a text file with two lines per exchange: `>` and the hex
+//! bytes of the command frame sent, then `<` and the hex bytes of the
+//! response frame, or `!` alone when the transport failed. Lines starting with `#`
+//! are comments.
+
+use crate::{command::EccCommand, transport::Transport, Error, Result};
+use bytes::BytesMut;
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::Path,
+    time::{Duration, Instant},
+};
+
+/// Passes everything through to the wrapped transport and logs every
+/// command and response frame to a writer:
+///
+/// ```no_run
+/// # fn main() -> ecc608_linux::Result {
+/// use ecc608_linux::{Ecc, RecordingTransport, TransportProtocol};
+///
+/// let transport = TransportProtocol::from_path("/dev/i2c-1", 0x60)?;
+/// let mut ecc = Ecc::new(RecordingTransport::create(transport, "session.txt")?);
+/// ecc.get_serial()?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// The frames of commands that write keys in the clear end up in the
+/// recording, only record with test keys.
+pub struct RecordingTransport<T, W = BufWriter<File>> {
+    inner: T,
+    log: W,
+}
+
+impl<T> RecordingTransport<T> {
+    /// Records to a new file at the given path.
+    pub fn create<P: AsRef<Path>>(inner: T, path: P) -> Result<Self> {
+        Ok(Self::new(inner, BufWriter::new(File::create(path)?)))
+    }
+}
+
+impl<T, W: Write> RecordingTransport<T, W> {
+    pub fn new(inner: T, log: W) -> Self {
+        Self { inner, log }
+    }
+
+    pub fn into_inner(self) -> (T, W) {
+        (self.inner, self.log)
+    }
+
+    fn record(&mut self, sent: &[u8], response: Option<&[u8]>) -> io::Result<()> {
+        match response {
+            Some(response) => writeln!(self.log, "> {}\n< {}", hex(sent), hex(response))?,
+            None => writeln!(self.log, "> {}\n!", hex(sent))?,
+        }
+        // keep the exchanges up to a crash
+        self.log.flush()
+    }
+}
+
+impl<T: Transport, W: Write> Transport for RecordingTransport<T, W> {
+    fn send_wake(&mut self, wake_delay: Duration) -> Result {
+        self.inner.send_wake(wake_delay)
+    }
+
+    fn send_sleep(&mut self) {
+        self.inner.send_sleep()
+    }
+
+    fn send_idle(&mut self) -> Result {
+        self.inner.send_idle()
+    }
+
+    fn send_recv_buf(&mut self, delay: Duration, buf: &mut BytesMut) -> Result {
+        let sent = buf.to_vec();
+        let result = self.inner.send_recv_buf(delay, buf);
+        self.record(&sent, result.as_ref().ok().map(|_| &buf[..]))?;
+        result
+    }
+
+    fn command_duration(&self, command: &EccCommand) -> Duration {
+        self.inner.command_duration(command)
+    }
+
+    fn put_command_flag(&self) -> u8 {
+        self.inner.put_command_flag()
+    }
+
+    fn set_address(&mut self, address: u16) -> Result {
+        self.inner.set_address(address)
+    }
+
+    fn set_deadline(&mut self, deadline: Option<Instant>) {
+        self.inner.set_deadline(deadline)
+    }
+}
+
+/// One recorded command frame and its response, `None` for a transport
+/// error
+#[derive(Debug, Clone)]
+struct Exchange {
+    sent: Vec<u8>,
+    response: Option<Vec<u8>>,
+}
+
+/// Answers commands with the responses of a recording made with
+/// [`RecordingTransport`]. Every command has to match the recorded frame,
+/// or it fails with [`Error::ReplayMismatch`]. Recorded transport errors
+/// come back as [`Error::Timeout`].
+///
+/// Commands whose frames depend on the host, like a challenge from the
+/// host's random source, differ from the recording and can't be replayed.
+#[derive(Debug)]
+pub struct ReplayTransport {
+    exchanges: VecDeque<Exchange>,
+    command_flag: u8,
+    replayed: usize,
+}
+
+impl ReplayTransport {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::from_reader(BufReader::new(File::open(path)?))
+    }
+
+    pub fn from_reader<R: BufRead>(reader: R) -> Result<Self> {
+        let mut exchanges = VecDeque::new();
+        let mut sent = None;
+        for line in reader.lines() {
+            let line = line?;
+            let line = line.trim();
+            match line.as_bytes().first() {
+                None | Some(b'#') => (),
+                Some(b'>') if sent.is_none() => sent = Some(unhex(&line[1..])?),
+                Some(b'<') | Some(b'!') if sent.is_some() => exchanges.push_back(Exchange {
+                    sent: sent.take().unwrap_or_default(),
+                    response: match line.starts_with('<') {
+                        true => Some(unhex(&line[1..])?),
+                        false => None,
+                    },
+                }),
+                _ => return Err(invalid_recording(line)),
+            }
+        }
+        let command_flag = exchanges
+            .front()
+            .and_then(|exchange| exchange.sent.first().copied())
+            .unwrap_or(0x03);
+        Ok(Self {
+            exchanges,
+            command_flag,
+            replayed: 0,
+        })
+    }
+
+    /// The number of recorded exchanges not replayed yet
+    pub fn remaining(&self) -> usize {
+        self.exchanges.len()
+    }
+}
+
+impl Transport for ReplayTransport {
+    fn send_wake(&mut self, _wake_delay: Duration) -> Result {
+        Ok(())
+    }
+
+    fn send_sleep(&mut self) {}
+
+    fn send_idle(&mut self) -> Result {
+        Ok(())
+    }
+
+    fn send_recv_buf(&mut self, _delay: Duration, buf: &mut BytesMut) -> Result {
+        let index = self.replayed;
+        match self.exchanges.front() {
+            Some(exchange) if exchange.sent == buf[..] => (),
+            _ => return Err(Error::replay_mismatch(index)),
+        }
+        self.replayed += 1;
+        match self
+            .exchanges
+            .pop_front()
+            .and_then(|exchange| exchange.response)
+        {
+            Some(response) => {
+                buf.clear();
+                buf.extend_from_slice(&response);
+                Ok(())
+            }
+            None => Err(Error::timeout()),
+        }
+    }
+
+    fn command_duration(&self, _command: &EccCommand) -> Duration {
+        Duration::ZERO
+    }
+
+    fn put_command_flag(&self) -> u8 {
+        self.command_flag
+    }
+
+    fn set_address(&mut self, _address: u16) -> Result {
+        Ok(())
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn unhex(text: &str) -> Result<Vec<u8>> {
+    text.split_whitespace()
+        .map(|byte| u8::from_str_radix(byte, 16).map_err(|_| invalid_recording(text)))
+        .collect()
+}
+
+fn invalid_recording(line: &str) -> Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("invalid recording line: {}", line),
+    )
+    .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{transport::MockTransport, Ecc};
+
+    #[test]
+    fn record_replay() {
+        let mock = MockTransport::default()
+            .respond(&[0x42; 32])
+            .expect_error(&[0x03, 0x07, 0x1B, 0x00, 0x00, 0x00, 0x24, 0xCD])
+            .respond(&[0x43; 32]);
+        let mut ecc = Ecc::new(RecordingTransport::new(mock, vec![]));
+        ecc.random().expect("random");
+        ecc.random().expect("random");
+        let recording = ecc.transport().log.clone();
+        // the failed attempt is recorded too
+        assert_eq!(6, (&recording[..]).lines().count());
+
+        let mut ecc = Ecc::new(ReplayTransport::from_reader(&recording[..]).expect("replay"));
+        assert_eq!(&[0x42; 32], &ecc.random().expect("random")[..]);
+        assert_eq!(&[0x43; 32], &ecc.random().expect("random")[..]);
+        assert_eq!(0, ecc.transport().remaining());
+
+        let mut ecc = Ecc::new(ReplayTransport::from_reader(&recording[..]).expect("replay"));
+        assert!(matches!(
+            ecc.get_serial(),
+            Err(Error::ReplayMismatch { index: 0 })
+        ));
+    }
+}
@@ -0,0 +1,65 @@
+//! A [`rustls`] signing key backed by a private key on the chip, so the
+//! handshake signature of a TLS client or server is made by the ECC.
+
+use crate::{transport::Transport, Ecc, Signature, TransportProtocol};
+use rustls::{
+    sign::{Signer, SigningKey},
+    Error, SignatureAlgorithm, SignatureScheme,
+};
+use std::{
+    convert::TryFrom,
+    fmt,
+    sync::{Arc, Mutex},
+};
+
+/// Signs TLS handshakes with the P256 private key in a slot of a shared
+/// [`Ecc`], using the `ecdsa_secp256r1_sha256` scheme. Pair it with the
+/// certificate chain for the key in a [`rustls::sign::CertifiedKey`].
+pub struct EccSigningKey<T = TransportProtocol> {
+    ecc: Arc<Mutex<Ecc<T>>>,
+    key_slot: u8,
+}
+
+impl<T> EccSigningKey<T> {
+    pub fn new(ecc: Arc<Mutex<Ecc<T>>>, key_slot: u8) -> Self {
+        Self { ecc, key_slot }
+    }
+}
+
+impl<T> fmt::Debug for EccSigningKey<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EccSigningKey")
+            .field("key_slot", &self.key_slot)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T: Transport + Send + 'static> SigningKey for EccSigningKey<T> {
+    fn choose_scheme(&self, offered: &[SignatureScheme]) -> Option<Box<dyn Signer>> {
+        if !offered.contains(&SignatureScheme::ECDSA_NISTP256_SHA256) {
+            return None;
+        }
+        Some(Box::new(Self::new(self.ecc.clone(), self.key_slot)))
+    }
+
+    fn algorithm(&self) -> SignatureAlgorithm {
+        SignatureAlgorithm::ECDSA
+    }
+}
+
+impl<T: Transport + Send + 'static> Signer for EccSigningKey<T> {
+    /// Signs the SHA256 digest of the message and returns the DER encoded
+    /// signature TLS expects.
+    fn sign(&self, message: &[u8]) -> Result<Vec<u8>, Error> {
+        let mut ecc = self.ecc.lock().unwrap_or_else(|err| err.into_inner());
+        let signature = ecc
+            .sign(self.key_slot, message)
+            .and_then(Signature::try_from)
+            .map_err(|err| Error::General(format!("ecc signing failed: {}", err)))?;
+        Ok(signature.to_der())
+    }
+
+    fn scheme(&self) -> SignatureScheme {
+        SignatureScheme::ECDSA_NISTP256_SHA256
+    }
+}
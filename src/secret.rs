@@ -0,0 +1,55 @@
+//! A wrapper for buffers holding keys and other secret material. With the
+//! `zeroize` feature the bytes are zeroed when the wrapper is dropped, so
+//! they don't survive in freed memory. Without it the wrapper does nothing.
+
+use bytes::BytesMut;
+use std::ops::{Deref, DerefMut};
+#[cfg(feature = "zeroize")]
+use zeroize::Zeroize;
+
+/// Owns a secret buffer and wipes it on drop
+pub(crate) struct Secret<T: Wipe>(pub(crate) T);
+
+/// Buffers that can be overwritten with zeroes
+pub(crate) trait Wipe {
+    fn wipe(&mut self);
+}
+
+impl<const N: usize> Wipe for [u8; N] {
+    fn wipe(&mut self) {
+        #[cfg(feature = "zeroize")]
+        self.zeroize();
+    }
+}
+
+impl Wipe for BytesMut {
+    /// Wipes the spare capacity as well, which holds whatever was in the
+    /// buffer before it was cleared or truncated.
+    fn wipe(&mut self) {
+        #[cfg(feature = "zeroize")]
+        {
+            self.as_mut().zeroize();
+            self.spare_capacity_mut().zeroize();
+        }
+    }
+}
+
+impl<T: Wipe> Drop for Secret<T> {
+    fn drop(&mut self) {
+        self.0.wipe();
+    }
+}
+
+impl<T: Wipe> Deref for Secret<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: Wipe> DerefMut for Secret<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
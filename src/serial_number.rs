@@ -0,0 +1,71 @@
+use crate::{Error, Result};
+#[cfg(feature = "serde")]
+use serde_derive::Deserialize;
+use serde_derive::Serialize;
+use std::{convert::TryFrom, fmt};
+
+/// The 9 byte serial number of an ECC. The first two bytes are always
+/// `[0x01, 0x23]` and the last byte is always `0xEE`.
+#[derive(Serialize, Debug, PartialEq, Eq, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(Deserialize))]
+pub struct SerialNumber(pub [u8; 9]);
+
+impl SerialNumber {
+    /// Returns the serial number as lower case hex without separators.
+    pub fn to_hex(&self) -> String {
+        self.0.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+}
+
+impl fmt::Display for SerialNumber {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, b) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(":")?;
+            }
+            write!(f, "{:02x}", b)?;
+        }
+        Ok(())
+    }
+}
+
+impl AsRef<[u8]> for SerialNumber {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl TryFrom<&[u8]> for SerialNumber {
+    type Error = Error;
+
+    /// Parses a 9 byte serial number, failing if the fixed bytes don't match
+    /// which points to a bad read.
+    fn try_from(v: &[u8]) -> Result<Self> {
+        if v.len() != 9 {
+            return Err(Error::invalid_length(9, v.len()));
+        }
+        if v[..2] != [0x01, 0x23] || v[8] != 0xEE {
+            return Err(Error::invalid_serial_number());
+        }
+        let mut result = [0u8; 9];
+        result.copy_from_slice(v);
+        Ok(Self(result))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format() {
+        let raw = [0x01, 0x23, 0xAB, 0x00, 0x10, 0x20, 0x30, 0x40, 0xEE];
+        let serial = SerialNumber::try_from(&raw[..]).expect("serial number");
+        assert_eq!("01:23:ab:00:10:20:30:40:ee", serial.to_string());
+        assert_eq!("0123ab0010203040ee", serial.to_hex());
+
+        let mut bad = raw;
+        bad[8] = 0xEF;
+        assert!(SerialNumber::try_from(&bad[..]).is_err());
+    }
+}
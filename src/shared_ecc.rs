@@ -0,0 +1,103 @@
+//! An [`Ecc`] shared between threads, with every call made under one lock.
+
+use crate::{transport::Transport, Ecc, Session, TransportProtocol};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// A cloneable handle to an [`Ecc`] behind an `Arc<Mutex>`. Each call holds
+/// the lock for the whole wake, command and sleep, so commands of different
+/// threads never interleave:
+///
+/// ```no_run
+/// # fn main() -> ecc608_linux::Result {
+/// use ecc608_linux::{Ecc, SharedEcc};
+///
+/// let ecc = SharedEcc::new(Ecc::from_path("/dev/i2c-1", 0x60)?);
+/// let worker = ecc.clone();
+/// std::thread::spawn(move || worker.with(|ecc| ecc.random()));
+/// let signature = ecc.with(|ecc| ecc.sign(0, b"message"))?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// A thread that panics while holding the lock leaves the chip usable, the
+/// poisoned lock is taken over by the next caller.
+pub struct SharedEcc<T = TransportProtocol> {
+    ecc: Arc<Mutex<Ecc<T>>>,
+}
+
+impl<T> Clone for SharedEcc<T> {
+    fn clone(&self) -> Self {
+        Self {
+            ecc: self.ecc.clone(),
+        }
+    }
+}
+
+impl<T> From<Arc<Mutex<Ecc<T>>>> for SharedEcc<T> {
+    fn from(ecc: Arc<Mutex<Ecc<T>>>) -> Self {
+        Self { ecc }
+    }
+}
+
+impl<T> SharedEcc<T> {
+    pub fn new(ecc: Ecc<T>) -> Self {
+        Self {
+            ecc: Arc::new(Mutex::new(ecc)),
+        }
+    }
+
+    /// Returns the shared mutex, for the types that take one like
+    /// `EccKeyPair` and `EccSigningKey`.
+    pub fn to_arc(&self) -> Arc<Mutex<Ecc<T>>> {
+        self.ecc.clone()
+    }
+
+    /// Locks the chip until the guard is dropped, for a sequence of calls
+    /// no other thread may come between.
+    pub fn lock(&self) -> MutexGuard<'_, Ecc<T>> {
+        self.ecc.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// Runs `f` with the chip locked.
+    pub fn with<R>(&self, f: impl FnOnce(&mut Ecc<T>) -> R) -> R {
+        f(&mut self.lock())
+    }
+}
+
+impl<T: Transport> SharedEcc<T> {
+    /// Runs `f` in a [`Session`], see [`Ecc::session`]. The lock is held for
+    /// the whole session so the TempKey state built up by `f` can't be
+    /// disturbed by other threads, and is released after the chip is put to
+    /// sleep.
+    pub fn session<R>(&self, f: impl FnOnce(&mut Session<'_, T>) -> R) -> R {
+        let mut ecc = self.lock();
+        let mut session = ecc.session();
+        f(&mut session)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::MockTransport;
+
+    #[test]
+    fn session_holds_lock() {
+        let ecc = SharedEcc::new(Ecc::new(MockTransport {
+            responses: vec![MockTransport::frame(&[0x42; 32]); 3].into(),
+            ..Default::default()
+        }));
+        let other = ecc.clone();
+        let random = std::thread::spawn(move || other.with(|ecc| ecc.random()))
+            .join()
+            .expect("thread");
+        assert_eq!(&[0x42; 32], &random.expect("random")[..]);
+
+        ecc.session(|session| {
+            assert!(ecc.ecc.try_lock().is_err());
+            session.random().expect("random");
+            session.random().expect("random");
+        });
+        assert!(ecc.ecc.try_lock().is_ok());
+    }
+}
@@ -0,0 +1,140 @@
+use crate::{Error, Result};
+use bytes::Bytes;
+use std::convert::TryFrom;
+
+/// A raw P256 ECDSA signature (`r || s`) as returned by the Sign command.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Signature(pub [u8; 64]);
+
+impl Signature {
+    pub fn r(&self) -> &[u8] {
+        &self.0[..32]
+    }
+
+    pub fn s(&self) -> &[u8] {
+        &self.0[32..]
+    }
+
+    /// Returns the ASN.1 DER encoding of the signature, a sequence of the two
+    /// integers r and s.
+    pub fn to_der(&self) -> Vec<u8> {
+        let r = der_integer(self.r());
+        let s = der_integer(self.s());
+        let mut result = Vec::with_capacity(2 + r.len() + s.len());
+        result.push(0x30);
+        result.push((r.len() + s.len()) as u8);
+        result.extend_from_slice(&r);
+        result.extend_from_slice(&s);
+        result
+    }
+
+    /// Converts the signature to a p256 signature. Fails if r or s are zero or
+    /// not below the curve order.
+    #[cfg(feature = "p256")]
+    pub fn to_p256(&self) -> Result<p256::ecdsa::Signature> {
+        p256::ecdsa::Signature::from_slice(&self.0).map_err(|_| Error::invalid_signature())
+    }
+
+    /// Parses an ASN.1 DER encoded signature.
+    pub fn from_der(der: &[u8]) -> Result<Self> {
+        match der {
+            [0x30, len, body @ ..] if *len as usize == body.len() => {
+                let (r, rest) = parse_der_integer(body)?;
+                let (s, rest) = parse_der_integer(rest)?;
+                if !rest.is_empty() {
+                    return Err(Error::invalid_signature());
+                }
+                let mut result = [0u8; 64];
+                result[..32].copy_from_slice(&r);
+                result[32..].copy_from_slice(&s);
+                Ok(Self(result))
+            }
+            _ => Err(Error::invalid_signature()),
+        }
+    }
+}
+
+impl AsRef<[u8]> for Signature {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl TryFrom<&[u8]> for Signature {
+    type Error = Error;
+
+    fn try_from(v: &[u8]) -> Result<Self> {
+        if v.len() != 64 {
+            return Err(Error::invalid_length(64, v.len()));
+        }
+        let mut result = [0u8; 64];
+        result.copy_from_slice(v);
+        Ok(Self(result))
+    }
+}
+
+impl TryFrom<Bytes> for Signature {
+    type Error = Error;
+
+    fn try_from(v: Bytes) -> Result<Self> {
+        Self::try_from(&v[..])
+    }
+}
+
+/// Encodes an unsigned big endian integer as a DER INTEGER using the
+/// minimal number of bytes, with a leading zero if the high bit is set.
+fn der_integer(v: &[u8]) -> Vec<u8> {
+    let start = v.iter().position(|b| *b != 0).unwrap_or(v.len() - 1);
+    let v = &v[start..];
+    let pad = v[0] & 0x80 != 0;
+    let len = v.len() + pad as usize;
+    let mut result = Vec::with_capacity(2 + len);
+    result.push(0x02);
+    result.push(len as u8);
+    if pad {
+        result.push(0x00);
+    }
+    result.extend_from_slice(v);
+    result
+}
+
+/// Parses a non-negative DER INTEGER of at most 32 value bytes, returning it
+/// left padded to 32 bytes and the remaining input.
+fn parse_der_integer(der: &[u8]) -> Result<([u8; 32], &[u8])> {
+    let (len, rest) = match der {
+        [0x02, len, rest @ ..] if *len > 0 && *len as usize <= rest.len() => (*len as usize, rest),
+        _ => return Err(Error::invalid_signature()),
+    };
+    let (value, rest) = rest.split_at(len);
+    // negative values and non minimal encodings are invalid
+    if value[0] & 0x80 != 0 || (len > 1 && value[0] == 0 && value[1] & 0x80 == 0) {
+        return Err(Error::invalid_signature());
+    }
+    let value = match value {
+        [0x00, tail @ ..] if len == 33 => tail,
+        _ if len > 32 => return Err(Error::invalid_signature()),
+        _ => value,
+    };
+    let mut result = [0u8; 32];
+    result[32 - value.len()..].copy_from_slice(value);
+    Ok((result, rest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn der_round_trip() {
+        let mut raw = [0u8; 64];
+        raw[0] = 0x80; // r needs a leading zero
+        raw[31] = 0x01;
+        raw[33] = 0x7F; // s has a leading zero byte to strip
+        raw[63] = 0x02;
+        let signature = Signature(raw);
+        let der = signature.to_der();
+        assert_eq!(&[0x30, 0x44, 0x02, 0x21, 0x00, 0x80], &der[..6]);
+        assert_eq!(&[0x02, 0x1F, 0x7F], &der[37..40]);
+        assert_eq!(signature, Signature::from_der(&der).expect("signature"));
+    }
+}
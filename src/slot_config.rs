@@ -1,6 +1,10 @@
+use crate::config_zone::flags;
 use bitfield::bitfield;
 use bytes::Buf;
+#[cfg(feature = "serde")]
+use serde_derive::Deserialize;
 use serde_derive::Serialize;
+use std::fmt;
 
 bitfield! {
     pub struct ReadKey(u8);
@@ -220,9 +224,8 @@ impl From<PrivWriteConfig> for u8 {
 }
 
 bitfield! {
-    #[derive(PartialEq)]
+    #[derive(PartialEq, Clone, Copy)]
     pub struct SlotConfig(u16);
-    impl Debug;
     pub secret, set_secret: 15;
     pub encrypt_read, set_encrypt_read: 14;
     pub limited_use, set_limited_use: 13;
@@ -284,6 +287,80 @@ impl SlotConfig {
     {
         self._set_write_config(config.into())
     }
+
+    pub fn with_read_key(mut self, read_key: ReadKey) -> Self {
+        self.set_read_key(read_key);
+        self
+    }
+
+    pub fn with_no_mac(mut self, no_mac: bool) -> Self {
+        self.set_no_mac(no_mac);
+        self
+    }
+
+    pub fn with_limited_use(mut self, limited_use: bool) -> Self {
+        self.set_limited_use(limited_use);
+        self
+    }
+
+    pub fn with_encrypt_read(mut self, encrypt_read: bool) -> Self {
+        self.set_encrypt_read(encrypt_read);
+        self
+    }
+
+    pub fn with_secret(mut self, secret: bool) -> Self {
+        self.set_secret(secret);
+        self
+    }
+
+    pub fn with_write_key(mut self, write_key: u8) -> Self {
+        self.set_write_key(write_key);
+        self
+    }
+
+    pub fn with_write_config<C>(mut self, config: C) -> Self
+    where
+        C: Into<u8>,
+    {
+        self.set_write_config(config);
+        self
+    }
+}
+
+impl fmt::Debug for SlotConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SlotConfig")
+            .field("secret", &self.secret())
+            .field("encrypt_read", &self.encrypt_read())
+            .field("limited_use", &self.limited_use())
+            .field("no_mac", &self.no_mac())
+            .field("read_key", &self.read_key())
+            .field("write_config", &self._write_config())
+            .field("write_key", &self.write_key())
+            .finish()
+    }
+}
+
+/// Prints the raw value followed by the set flags and the key and write
+/// config fields, e.g. `0x9720 secret no_mac read_key=0x7 write_key=0
+/// write_config=0x2`.
+impl fmt::Display for SlotConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:#06x}{} read_key={:#x} write_key={} write_config={:#x}",
+            self.0,
+            flags(&[
+                ("secret", self.secret()),
+                ("encrypt_read", self.encrypt_read()),
+                ("limited_use", self.limited_use()),
+                ("no_mac", self.no_mac()),
+            ]),
+            u8::from(self.read_key()),
+            self.write_key(),
+            self._write_config(),
+        )
+    }
 }
 
 impl serde::ser::Serialize for SlotConfig {
@@ -310,7 +387,7 @@ impl serde::ser::Serialize for ReadKey {
         S: serde::ser::Serializer,
     {
         use serde::ser::SerializeStruct;
-        let mut state = serializer.serialize_struct("read_key", 7)?;
+        let mut state = serializer.serialize_struct("read_key", 4)?;
         state.serialize_field("external_signatures", &self.external_signatures())?;
         state.serialize_field("internal_signatures", &self.internal_signatures())?;
         state.serialize_field("ecdh_operation", &self.ecdh_operation())?;
@@ -318,3 +395,93 @@ impl serde::ser::Serialize for ReadKey {
         state.end()
     }
 }
+
+/// The named fields of a serialized [`SlotConfig`], with the write config
+/// left as its raw bits since their meaning depends on the command.
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+struct SlotConfigFields {
+    secret: bool,
+    encrypt_read: bool,
+    limited_use: bool,
+    no_mac: bool,
+    read_key: ReadKey,
+    write_config: u8,
+    write_key: u8,
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::de::Deserialize<'de> for SlotConfig {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::de::Deserializer<'de>,
+    {
+        let fields = SlotConfigFields::deserialize(deserializer)?;
+        Ok(SlotConfig::from(0)
+            .with_secret(fields.secret)
+            .with_encrypt_read(fields.encrypt_read)
+            .with_limited_use(fields.limited_use)
+            .with_no_mac(fields.no_mac)
+            .with_read_key(fields.read_key)
+            .with_write_config(fields.write_config)
+            .with_write_key(fields.write_key))
+    }
+}
+
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+struct ReadKeyFields {
+    external_signatures: bool,
+    internal_signatures: bool,
+    ecdh_operation: bool,
+    ecdh_write_slot: bool,
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::de::Deserialize<'de> for ReadKey {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::de::Deserializer<'de>,
+    {
+        let fields = ReadKeyFields::deserialize(deserializer)?;
+        let mut result = ReadKey(0);
+        result.set_external_signatures(fields.external_signatures);
+        result.set_internal_signatures(fields.internal_signatures);
+        result.set_ecdh_operation(fields.ecdh_operation);
+        result.set_ecdh_write_slot(fields.ecdh_write_slot);
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn with_fields() {
+        let config = SlotConfig::from(0)
+            .with_secret(true)
+            .with_encrypt_read(true)
+            .with_read_key(ReadKey::from(3))
+            .with_write_key(5)
+            .with_write_config(_WriteConfig::Encrypt);
+        assert_eq!(0xC345, u16::from(&config));
+        // the datasheet byte order, ReadKey and the flags come first
+        assert_eq!(config, SlotConfig::from(&[0xC3, 0x45][..]));
+        assert_eq!(0x9720, u16::from(SlotConfig::default()));
+        assert_eq!(
+            "0x9720 secret no_mac read_key=0x7 write_key=0 write_config=0x2",
+            SlotConfig::default().to_string()
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn serde_round_trip() {
+        let config = SlotConfig::from(0xC3B5);
+        let json = serde_json::to_string(&config).expect("serialize");
+        assert!(json.contains("\"encrypt_read\":true"));
+        let parsed: SlotConfig = serde_json::from_str(&json).expect("deserialize");
+        assert_eq!(config, parsed);
+    }
+}
@@ -0,0 +1,115 @@
+//! Ready made [`ConfigZone`]s for common use cases, modeled after the
+//! configurations Microchip documents for its pre-provisioned parts. They
+//! are meant as starting points to adjust before writing them with
+//! [`Ecc::write_config`](crate::Ecc::write_config).
+
+use crate::{
+    _WriteConfig, ConfigZone, ConfigZoneBuilder, KeyConfig, KeyConfigType, ReadKey, SlotConfig,
+};
+
+/// A slot holding a generated ECC private key usable for signing and ECDH
+fn private_key() -> (SlotConfig, KeyConfig) {
+    (SlotConfig::default(), KeyConfig::default())
+}
+
+/// A slot holding clear text data, like a public key or a certificate, that
+/// can be written until the slot is locked
+fn data(key_type: KeyConfigType) -> (SlotConfig, KeyConfig) {
+    let slot_config = SlotConfig::from(0)
+        .with_read_key(ReadKey::from(0))
+        .with_write_config(_WriteConfig::Always);
+    let key_config = KeyConfig::from(0)
+        .with_key_type(key_type)
+        .with_lockable(true);
+    (slot_config, key_config)
+}
+
+/// A 32 byte symmetric secret that can't be read or written in the clear
+fn secret(write_key: u8) -> (SlotConfig, KeyConfig) {
+    let slot_config = SlotConfig::from(0)
+        .with_secret(true)
+        .with_read_key(ReadKey::from(0))
+        .with_write_key(write_key)
+        .with_write_config(_WriteConfig::Encrypt);
+    let key_config = KeyConfig::from(0)
+        .with_key_type(KeyConfigType::NotEcc)
+        .with_lockable(true);
+    (slot_config, key_config)
+}
+
+fn with_slots(slots: Vec<(u8, (SlotConfig, KeyConfig))>) -> ConfigZoneBuilder {
+    slots.into_iter().fold(
+        ConfigZoneBuilder::new(),
+        |builder, (slot, (slot_config, key_config))| {
+            builder
+                .slot_config(slot, slot_config)
+                .key_config(slot, key_config)
+        },
+    )
+}
+
+/// A TLS client identity: the device private key in slot 0 and spare private
+/// keys in slots 1 to 4, general data in slot 8, the compressed device and
+/// signer certificates in slots 10 to 12 and the signer and root public keys
+/// in slots 13 to 15.
+pub fn tls_client_cert() -> ConfigZone {
+    let mut slots = vec![];
+    for slot in 0..=4 {
+        slots.push((slot, private_key()));
+    }
+    for slot in 5..=7 {
+        slots.push((slot, secret(6)));
+    }
+    for slot in 8..=12 {
+        slots.push((slot, data(KeyConfigType::NotEcc)));
+    }
+    for slot in 13..=15 {
+        slots.push((slot, data(KeyConfigType::Ecc)));
+    }
+    with_slots(slots).build().expect("valid template")
+}
+
+/// Secure boot with the firmware digest stored in slot 14 and the firmware
+/// signing public key in slot 15, next to a device private key in slot 0.
+pub fn secure_boot() -> ConfigZone {
+    let mut slots = vec![(0, private_key())];
+    for slot in 1..=13 {
+        slots.push((slot, data(KeyConfigType::NotEcc)));
+    }
+    slots.push((14, data(KeyConfigType::NotEcc)));
+    slots.push((15, data(KeyConfigType::Ecc)));
+    let mut config = with_slots(slots).build().expect("valid template");
+    // FullStore mode with the digest slot in bits 8 to 11 and the public key
+    // slot in bits 12 to 15
+    config.secure_boot = 0x03 | 14 << 8 | 15 << 12;
+    config
+}
+
+/// Symmetric authentication with a root secret in slot 3 that the host
+/// shares, and slots 4 to 7 for secrets written encrypted with it. The
+/// remaining slots hold clear text data.
+pub fn symmetric_auth() -> ConfigZone {
+    let mut slots = vec![(0, private_key())];
+    for slot in 1..=2 {
+        slots.push((slot, data(KeyConfigType::NotEcc)));
+    }
+    for slot in 3..=7 {
+        slots.push((slot, secret(3)));
+    }
+    for slot in 8..=15 {
+        slots.push((slot, data(KeyConfigType::NotEcc)));
+    }
+    with_slots(slots).build().expect("valid template")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn valid() {
+        for config in [tls_client_cert(), secure_boot(), symmetric_auth()] {
+            assert_eq!(Ok(()), config.validate());
+        }
+    }
+}
@@ -1,9 +1,13 @@
 use bytes::{BufMut, BytesMut};
-use std::{fs::File, thread, time::Duration};
+use std::{
+    fs::File,
+    io, mem, thread,
+    time::{Duration, Instant},
+};
 
 use crate::constants::{
-    ATCA_I2C_COMMAND_FLAG, ATCA_RSP_SIZE_MAX, ATCA_SWI_COMMAND_FLAG, ATCA_SWI_SLEEP_FLAG,
-    ATCA_SWI_TRANSMIT_FLAG, WAKE_DELAY,
+    ATCA_I2C_COMMAND_FLAG, ATCA_RSP_SIZE_MAX, ATCA_SWI_COMMAND_FLAG, ATCA_SWI_IDLE_FLAG,
+    ATCA_SWI_SLEEP_FLAG, ATCA_SWI_TRANSMIT_FLAG, ATCA_WAKE_RESPONSE, WAKE_DELAY,
 };
 use crate::{command::EccCommand, Error, Result};
 
@@ -18,16 +22,136 @@ const SWI_BIT_SEND_DELAY: Duration = Duration::from_micros(45);
 pub struct I2cTransport {
     port: I2c<File>,
     address: u16,
+    /// Set once the chip was put to sleep, so the next wake has to be
+    /// answered with the wake response
+    asleep: bool,
+    /// Set while a command has to be answered by a deadline
+    deadline: Option<Instant>,
 }
 
 pub struct SwiTransport {
     port: Box<dyn SerialPort>,
 }
-pub(crate) enum TransportProtocol {
+
+/// The bus an [`Ecc`](crate::Ecc) talks to the chip over. Implement this to
+/// run the command logic of this crate on top of a bus it doesn't support
+/// itself.
+pub trait Transport {
+    /// Wakes the chip and waits the given wake delay before returning.
+    fn send_wake(&mut self, wake_delay: Duration) -> Result;
+
+    /// Puts the chip to sleep, which clears its volatile state
+    fn send_sleep(&mut self);
+
+    /// Puts the chip in idle mode, which keeps its volatile state
+    fn send_idle(&mut self) -> Result;
+
+    /// Sends the command in `buf`, waits the given command execution time and
+    /// reads the response frame back into `buf`.
+    fn send_recv_buf(&mut self, delay: Duration, buf: &mut BytesMut) -> Result;
+
+    /// The time to wait for the given command to complete. Defaults to
+    /// typical execution times, some of them below the worst case maxima of
+    /// the datasheet. A response that isn't ready by then is polled for and
+    /// the command is retried.
+    fn command_duration(&self, command: &EccCommand) -> Duration {
+        Duration::from_micros(command_micros(command, false))
+    }
+
+    /// The word address byte that precedes a command on the bus
+    fn put_command_flag(&self) -> u8;
+
+    /// Retargets the transport to a new device address. Defaults to an error
+    /// for buses without addresses.
+    fn set_address(&mut self, _address: u16) -> Result {
+        Err(Error::invalid_address())
+    }
+
+    /// Stops polling for a response once the deadline has passed, until it
+    /// is cleared with `None`. Defaults to ignoring the deadline.
+    fn set_deadline(&mut self, _deadline: Option<Instant>) {}
+}
+
+/// The Linux I2C and single wire transports [`Ecc::from_path`](crate::Ecc::from_path)
+/// picks from.
+pub enum TransportProtocol {
     I2c(I2cTransport),
     Swi(SwiTransport),
 }
 
+/// A transport that replays scripted responses and records the command
+/// frames sent, to test code on top of [`Ecc`](crate::Ecc) without a chip.
+/// Wakes and sleeps always succeed and commands complete without delay.
+///
+/// ```
+/// use ecc608_linux::{Ecc, MockTransport};
+///
+/// // the Random command, followed by its 32 byte response
+/// let mock = MockTransport::default().expect(
+///     &[0x03, 0x07, 0x1B, 0x00, 0x00, 0x00, 0x24, 0xCD],
+///     &[0x42; 32],
+/// );
+/// let mut ecc = Ecc::new(mock);
+/// assert_eq!(&[0x42; 32], &ecc.random().unwrap()[..]);
+/// assert_eq!(1, ecc.transport().sent.len());
+/// ```
+#[cfg(any(test, feature = "test-util"))]
+#[derive(Debug, Default)]
+pub struct MockTransport {
+    /// Responses to return in order, `None` simulates a transport error
+    pub responses: std::collections::VecDeque<Option<Vec<u8>>>,
+    /// The command frames the responses answer, compared against the frames
+    /// sent in order, one per attempt. Each frame starts with the word address byte and ends
+    /// with the CRC. A frame sent while this is empty isn't checked.
+    pub expected: std::collections::VecDeque<Vec<u8>>,
+    /// The command frames sent, including retries
+    pub sent: Vec<Vec<u8>>,
+    /// The number of commands sent
+    pub attempts: usize,
+    /// The number of wakes sent
+    pub wakes: usize,
+    /// The number of sleeps sent
+    pub sleeps: usize,
+    /// The errors returned for `None` responses in order, a timeout once
+    /// these run out
+    pub errors: std::collections::VecDeque<Error>,
+    /// How long each command takes before it is answered
+    pub latency: Duration,
+}
+
+#[cfg(any(test, feature = "test-util"))]
+impl MockTransport {
+    /// Returns the response frame of a command that returned `data`
+    pub fn frame(data: &[u8]) -> Option<Vec<u8>> {
+        let mut frame = vec![data.len() as u8 + 3];
+        frame.extend_from_slice(data);
+        let crc = crate::command::crc(&frame);
+        frame.extend_from_slice(&crc.to_le_bytes());
+        Some(frame)
+    }
+
+    /// Queues a response frame returning `data` for the next command.
+    pub fn respond(mut self, data: &[u8]) -> Self {
+        self.responses.push_back(Self::frame(data));
+        self
+    }
+
+    /// Expects the next command to be sent as `command` and queues a
+    /// response frame returning `data` for it.
+    pub fn expect(mut self, command: &[u8], data: &[u8]) -> Self {
+        self.expected.push_back(command.to_vec());
+        self.respond(data)
+    }
+
+    /// Expects the next command to be sent as `command` and fails it with a
+    /// transport error, which [`Ecc`](crate::Ecc) retries.
+    pub fn expect_error(mut self, command: &[u8]) -> Self {
+        self.expected.push_back(command.to_vec());
+        self.responses.push_back(None);
+        self
+    }
+}
+
 impl TransportProtocol {
     pub fn from_path(path: &str, address: u16) -> Result<Self> {
         if path.starts_with("/dev/tty") {
@@ -41,53 +165,78 @@ impl TransportProtocol {
         }
     }
 
-    pub fn send_wake(&mut self) -> Result {
+    pub fn from_swi(path: &str) -> Result<Self> {
+        SwiTransport::new(path).map(Self::Swi)
+    }
+
+    /// Sends a command without waiting for the response. Used by callers that
+    /// wait for the command duration themselves.
+    pub fn send_buf(&mut self, buf: &[u8]) -> Result {
+        match self {
+            Self::I2c(i2c_handle) => i2c_handle.send_buf(i2c_handle.address, buf),
+            Self::Swi(swi_handle) => swi_handle.send_cmd_buf(buf),
+        }
+    }
+
+    /// Receives the response of a command sent with [`Self::send_buf`].
+    pub fn recv_buf(&mut self, buf: &mut BytesMut) -> Result {
+        match self {
+            Self::I2c(i2c_handle) => i2c_handle.recv_buf(buf),
+            Self::Swi(swi_handle) => swi_handle.recv_swi_buf(buf),
+        }
+    }
+}
+
+impl Transport for TransportProtocol {
+    /// Retargets an I2C transport to a new device address. Single wire
+    /// devices have no address.
+    fn set_address(&mut self, address: u16) -> Result {
         match self {
-            Self::I2c(i2c_handle) => i2c_handle.send_wake(),
-            Self::Swi(swi_handle) => swi_handle.send_wake(),
+            Self::I2c(i2c_handle) => i2c_handle.set_address(address),
+            Self::Swi(_) => Err(Error::invalid_address()),
+        }
+    }
+
+    /// Only the I2C transport polls for the response, the single wire one
+    /// reads it with the timeout of the serial port.
+    fn set_deadline(&mut self, deadline: Option<Instant>) {
+        if let Self::I2c(i2c_handle) = self {
+            i2c_handle.deadline = deadline;
         }
     }
 
-    pub fn send_sleep(&mut self) {
+    fn send_wake(&mut self, wake_delay: Duration) -> Result {
+        match self {
+            Self::I2c(i2c_handle) => i2c_handle.send_wake(wake_delay),
+            Self::Swi(swi_handle) => swi_handle.send_wake(wake_delay),
+        }
+    }
+
+    fn send_sleep(&mut self) {
         match self {
             Self::I2c(i2c_handle) => i2c_handle.send_sleep(),
             Self::Swi(swi_handle) => swi_handle.send_sleep(),
         }
     }
 
-    pub fn send_recv_buf(&mut self, delay: Duration, buf: &mut BytesMut) -> Result {
+    fn send_idle(&mut self) -> Result {
         match self {
-            Self::I2c(i2c_handle) => i2c_handle.send_recv_buf(delay, buf),
-            Self::Swi(swi_handle) => swi_handle.send_recv_buf(delay, buf),
+            Self::I2c(i2c_handle) => i2c_handle.send_idle(),
+            Self::Swi(swi_handle) => swi_handle.send_idle(),
         }
     }
 
-    pub fn command_duration(&self, command: &EccCommand) -> Duration {
-        let micros = match command {
-            EccCommand::Info => 500,
-            EccCommand::Read { .. } => 800,
-            EccCommand::Write { .. } => 8_000,
-            // ecc608b increases the default lock duration of 15_000 by about 30%
-            EccCommand::Lock { .. } => 19_500,
-            EccCommand::Nonce { .. } => 17_000,
-            EccCommand::Random => 15_000,
-            EccCommand::GenKey { .. } => match self {
-                Self::Swi(_) => 85_000,
-                Self::I2c(_) => 59_000,
-            },
-            EccCommand::Sign { .. } => match self {
-                Self::Swi(_) => 80_000,
-                Self::I2c(_) => 64_000,
-            },
-            EccCommand::Ecdh { .. } => match self {
-                Self::Swi(_) => 42_000,
-                Self::I2c(_) => 28_000,
-            },
-        };
-        Duration::from_micros(micros)
+    fn send_recv_buf(&mut self, delay: Duration, buf: &mut BytesMut) -> Result {
+        self.send_buf(&buf[..])?;
+        thread::sleep(delay);
+        self.recv_buf(buf)
     }
 
-    pub fn put_command_flag(&self) -> u8 {
+    fn command_duration(&self, command: &EccCommand) -> Duration {
+        Duration::from_micros(command_micros(command, matches!(self, Self::Swi(_))))
+    }
+
+    fn put_command_flag(&self) -> u8 {
         match self {
             Self::I2c(_) => ATCA_I2C_COMMAND_FLAG,
             Self::Swi(_) => ATCA_SWI_COMMAND_FLAG,
@@ -95,28 +244,174 @@ impl TransportProtocol {
     }
 }
 
+#[cfg(any(test, feature = "test-util"))]
+impl Transport for MockTransport {
+    fn send_wake(&mut self, _wake_delay: Duration) -> Result {
+        self.wakes += 1;
+        Ok(())
+    }
+
+    fn send_sleep(&mut self) {
+        self.sleeps += 1;
+    }
+
+    fn send_idle(&mut self) -> Result {
+        Ok(())
+    }
+
+    /// Panics when the frame doesn't match the next expected frame.
+    fn send_recv_buf(&mut self, _delay: Duration, buf: &mut BytesMut) -> Result {
+        self.attempts += 1;
+        if let Some(expected) = self.expected.pop_front() {
+            assert_eq!(expected, &buf[..], "unexpected command frame");
+        }
+        self.sent.push(buf.to_vec());
+        thread::sleep(self.latency);
+        match self.responses.pop_front().flatten() {
+            Some(response) => {
+                buf.clear();
+                buf.extend_from_slice(&response);
+                Ok(())
+            }
+            None => Err(self.errors.pop_front().unwrap_or_else(Error::timeout)),
+        }
+    }
+
+    fn command_duration(&self, _command: &EccCommand) -> Duration {
+        Duration::ZERO
+    }
+
+    fn put_command_flag(&self) -> u8 {
+        ATCA_I2C_COMMAND_FLAG
+    }
+}
+
+/// The typical execution time of a command in microseconds, several of these
+/// are shorter than the cryptoauthlib maxima. The ECC runs some of the ECC
+/// operations slower in single wire mode.
+fn command_micros(command: &EccCommand, swi: bool) -> u64 {
+    match command {
+        EccCommand::Info { .. } => 500,
+        EccCommand::Read { .. } => 800,
+        EccCommand::Write { .. } | EccCommand::WriteEncrypted { .. } => 8_000,
+        // ecc608b increases the default lock duration of 15_000 by about 30%
+        EccCommand::Lock { .. } | EccCommand::LockSlot { .. } => 19_500,
+        EccCommand::Nonce { .. } => 17_000,
+        EccCommand::Random { .. } => 15_000,
+        EccCommand::Counter { .. } => 20_000,
+        EccCommand::Sha { .. } => 9_000,
+        EccCommand::GenDig { .. } => 11_000,
+        EccCommand::DeriveKey { .. } => 50_000,
+        EccCommand::PrivWrite { .. } => 48_000,
+        EccCommand::Mac { .. } => 24_000,
+        EccCommand::CheckMac { .. } => 20_000,
+        // self test runs all selected tests back to back
+        EccCommand::SelfTest { .. } => 200_000,
+        // datasheet max is 160ms, leave some headroom
+        EccCommand::SecureBoot { .. } => 200_000,
+        EccCommand::UpdateExtra { .. } => 10_000,
+        EccCommand::Aes { .. } => 27_000,
+        EccCommand::Kdf { .. } => 165_000,
+        EccCommand::GenKey { .. } | EccCommand::GenKeyDigest { .. } => {
+            if swi {
+                85_000
+            } else {
+                59_000
+            }
+        }
+        EccCommand::Sign { .. } => {
+            if swi {
+                80_000
+            } else {
+                64_000
+            }
+        }
+        EccCommand::Ecdh { .. } => {
+            if swi {
+                42_000
+            } else {
+                28_000
+            }
+        }
+        EccCommand::VerifyExtern { .. } | EccCommand::VerifyStored { .. } => {
+            if swi {
+                90_000
+            } else {
+                72_000
+            }
+        }
+    }
+}
+
 impl I2cTransport {
     fn new(path: &str, address: u16) -> Result<Self> {
         let mut port = I2c::from_path(path)?;
         port.smbus_set_slave_address(address, false)?;
 
-        Ok(Self { port, address })
+        Ok(Self {
+            port,
+            address,
+            asleep: false,
+            deadline: None,
+        })
+    }
+
+    fn set_address(&mut self, address: u16) -> Result {
+        self.port.smbus_set_slave_address(address, false)?;
+        self.address = address;
+        self.asleep = false;
+        Ok(())
     }
 
-    fn send_wake(&mut self) -> Result {
-        let _ = self.send_buf(0, &[0]);
-        thread::sleep(WAKE_DELAY);
+    /// Wakes the chip. The first wake after a sleep has to be answered with
+    /// the wake response, anything else means no chip is responding. Other
+    /// wakes may reach a chip that is still awake, so they only require the
+    /// read to be acknowledged.
+    fn send_wake(&mut self, wake_delay: Duration) -> Result {
+        let response = self.wake_response(wake_delay)?;
+        if mem::take(&mut self.asleep) && response != ATCA_WAKE_RESPONSE {
+            return Err(Error::no_device());
+        }
         Ok(())
     }
 
+    /// Wakes the chip and reads back its response. A chip that is already
+    /// awake ignores the wake and answers with its output buffer instead of
+    /// the wake response. A read that isn't acknowledged at all is treated
+    /// as a missing device.
+    fn wake_response(&mut self, wake_delay: Duration) -> Result<[u8; 4]> {
+        let _ = self.send_buf(0, &[0]);
+        thread::sleep(wake_delay);
+        let mut buf = [0u8; 4];
+        let msg = i2c_linux::Message::Read {
+            address: self.address,
+            data: &mut buf,
+            flags: Default::default(),
+        };
+        self.port
+            .i2c_transfer(&mut [msg])
+            .map_err(|_| Error::no_device())?;
+        Ok(buf)
+    }
+
     fn send_sleep(&mut self) {
-        let _ = self.send_buf(self.address, &[1]);
+        self.asleep = self.send_buf(self.address, &[1]).is_ok();
     }
 
-    fn send_recv_buf(&mut self, delay: Duration, buf: &mut BytesMut) -> Result {
-        self.send_buf(self.address, &buf[..])?;
-        thread::sleep(delay);
-        self.recv_buf(buf)
+    fn send_idle(&mut self) -> Result {
+        self.send_buf(self.address, &[2])
+    }
+
+    /// Returns whether a device at the given address on the bus answers a wake
+    /// with the expected wake response.
+    pub fn probe(path: &str, address: u16) -> Result<bool> {
+        let mut transport = Self::new(path, address)?;
+        let answered =
+            matches!(transport.wake_response(WAKE_DELAY), Ok(buf) if buf == ATCA_WAKE_RESPONSE);
+        if answered {
+            transport.send_sleep();
+        }
+        Ok(answered)
     }
 
     fn send_buf(&mut self, address: u16, buf: &[u8]) -> Result {
@@ -126,24 +421,38 @@ impl I2cTransport {
             flags: Default::default(),
         };
 
-        self.port.i2c_transfer(&mut [write_msg])?;
+        self.port
+            .i2c_transfer(&mut [write_msg])
+            .map_err(i2c_error)?;
         Ok(())
     }
 
     fn recv_buf(&mut self, buf: &mut BytesMut) -> Result {
         buf.resize(ATCA_RSP_SIZE_MAX as usize, 0);
         buf[0] = 0xff;
+        let mut result = Ok(());
         for _retry in 0..RECV_RETRIES {
             let msg = i2c_linux::Message::Read {
                 address: self.address,
                 data: buf,
                 flags: Default::default(),
             };
-            if self.port.i2c_transfer(&mut [msg]).is_ok() {
+            result = self.port.i2c_transfer(&mut [msg]).map_err(i2c_error);
+            if result.is_ok() {
                 break;
             }
-            thread::sleep(RECV_RETRY_WAIT);
+            let wait = match self.deadline {
+                Some(deadline) => {
+                    RECV_RETRY_WAIT.min(deadline.saturating_duration_since(Instant::now()))
+                }
+                None => RECV_RETRY_WAIT,
+            };
+            if wait.is_zero() {
+                break;
+            }
+            thread::sleep(wait);
         }
+        result?;
         let count = buf[0] as usize;
         if count == 0xff {
             return Err(Error::timeout());
@@ -153,6 +462,29 @@ impl I2cTransport {
     }
 }
 
+/// Maps the errors of a failed I2C transfer to the errors the retry loop can
+/// tell apart, using the Linux errno values.
+///
+/// The chip doesn't acknowledge its address while it is asleep or still
+/// executing a command, and bus drivers report that NACK as ENXIO, EREMOTEIO
+/// or EIO. Those are retryable timeouts here; only a wake that isn't
+/// answered means there is no device.
+fn i2c_error(err: io::Error) -> Error {
+    const EIO: i32 = 5;
+    const ENXIO: i32 = 6;
+    const EAGAIN: i32 = 11;
+    const EBUSY: i32 = 16;
+    const ETIMEDOUT: i32 = 110;
+    const EREMOTEIO: i32 = 121;
+    match err.raw_os_error() {
+        Some(ENXIO) | Some(EREMOTEIO) | Some(EIO) => Error::timeout(),
+        // the adapter gave up on a bus that is held low
+        Some(ETIMEDOUT) => Error::bus_error(err),
+        Some(EAGAIN) | Some(EBUSY) => Error::bus_busy(),
+        _ => Error::Io(err),
+    }
+}
+
 impl SwiTransport {
     fn new(path: &str) -> Result<Self> {
         let port = serialport::new(path, SWI_DEFAULT_BAUDRATE)
@@ -165,17 +497,20 @@ impl SwiTransport {
         Ok(Self { port })
     }
 
-    fn send_wake(&mut self) -> Result {
+    fn send_wake(&mut self, wake_delay: Duration) -> Result {
         if let Err(_err) = self.port.as_mut().set_baud_rate(SWI_WAKE_BAUDRATE) {
             return Err(Error::timeout());
         }
 
         let _ = self.port.as_mut().write(&[0]);
 
-        thread::sleep(WAKE_DELAY);
+        thread::sleep(wake_delay);
         let _ = self.port.as_mut().set_baud_rate(SWI_DEFAULT_BAUDRATE);
         let _ = self.port.as_mut().clear(ClearBuffer::All);
-        Ok(())
+        // nothing answering the transmit flag means there is no chip on the
+        // line, an already awake chip answers with its output buffer
+        let mut buf = BytesMut::with_capacity(ATCA_RSP_SIZE_MAX as usize);
+        self.recv_swi_buf(&mut buf).map_err(|_| Error::no_device())
     }
 
     fn send_sleep(&mut self) {
@@ -184,12 +519,17 @@ impl SwiTransport {
         thread::sleep(SWI_BIT_SEND_DELAY * 8);
     }
 
-    fn send_recv_buf(&mut self, delay: Duration, buf: &mut BytesMut) -> Result {
+    fn send_idle(&mut self) -> Result {
+        let idle_encoded = self.encode_uart_to_swi(&[ATCA_SWI_IDLE_FLAG]);
+        self.port.as_mut().write_all(&idle_encoded)?;
+        thread::sleep(SWI_BIT_SEND_DELAY * 8);
+        Ok(())
+    }
+
+    fn send_cmd_buf(&mut self, buf: &[u8]) -> Result {
         let _ = self.port.as_mut().clear(ClearBuffer::All);
         let swi_msg = self.encode_uart_to_swi(buf);
-        self.send_swi_buf(&swi_msg)?;
-        thread::sleep(delay);
-        self.recv_swi_buf(buf)
+        self.send_swi_buf(&swi_msg)
     }
 
     fn send_swi_buf(&mut self, buf: &[u8]) -> Result {
@@ -275,3 +615,21 @@ impl SwiTransport {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn i2c_errno() {
+        // a NACK of a busy chip is retried rather than taken as no device
+        for errno in [5, 6, 121] {
+            let err = i2c_error(io::Error::from_raw_os_error(errno));
+            assert!(matches!(err, Error::Timeout) && err.is_retryable());
+        }
+        let err = i2c_error(io::Error::from_raw_os_error(110));
+        assert!(matches!(err, Error::BusError(_)) && !err.is_retryable());
+        let err = i2c_error(io::Error::from_raw_os_error(11));
+        assert!(matches!(err, Error::BusBusy) && err.is_retryable());
+    }
+}
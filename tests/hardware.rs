@@ -0,0 +1,181 @@
+#![cfg(feature = "hardware-tests")]
+
+use ecc608_linux::{templates, Address, Ecc, Error, Transport, TransportProtocol};
+use std::{env, time::Duration};
+
+fn ecc() -> Ecc {
+    let path = env::var("ECC_PATH").unwrap_or_else(|_| "/dev/i2c-1".to_string());
+    let address = env::var("ECC_ADDRESS")
+        .ok()
+        .and_then(|v| u16::from_str_radix(v.trim_start_matches("0x"), 16).ok())
+        .unwrap_or(0x60);
+    Ecc::from_path(&path, address).expect("ecc device")
+}
+
+#[test]
+fn wake_response() {
+    const WAKE_DELAY: Duration = Duration::from_micros(1500);
+
+    let path = env::var("ECC_PATH").unwrap_or_else(|_| "/dev/i2c-1".to_string());
+    let address = env::var("ECC_ADDRESS")
+        .ok()
+        .and_then(|v| u16::from_str_radix(v.trim_start_matches("0x"), 16).ok())
+        .unwrap_or(0x60);
+    // a chip woken from sleep has to answer with the wake response
+    let mut transport = TransportProtocol::from_path(&path, address).expect("transport");
+    transport.send_sleep();
+    assert!(transport.send_wake(WAKE_DELAY).is_ok());
+    transport.send_sleep();
+    assert!(ecc().info_revision().is_ok());
+    // ECC_ABSENT_ADDRESS is an address on the same bus with no chip behind it
+    let absent = match env::var("ECC_ABSENT_ADDRESS")
+        .ok()
+        .and_then(|v| u16::from_str_radix(v.trim_start_matches("0x"), 16).ok())
+    {
+        Some(address) => address,
+        None => return,
+    };
+    let mut transport = TransportProtocol::from_path(&path, absent).expect("transport");
+    transport.send_sleep();
+    assert!(matches!(
+        transport.send_wake(WAKE_DELAY),
+        Err(Error::NoDevice)
+    ));
+    let mut ecc = Ecc::from_path(&path, absent).expect("ecc handle");
+    assert!(matches!(ecc.info_revision(), Err(Error::NoDevice)));
+}
+
+#[test]
+fn sha_matches_host() {
+    use sha2::{Digest, Sha256};
+
+    let data: Vec<u8> = (0..300).map(|n| n as u8).collect();
+    let mut ecc = ecc();
+    ecc.sha_start().expect("sha start");
+    ecc.sha_update(&data[..100]).expect("sha update");
+    ecc.sha_update(&data[100..250]).expect("sha update");
+    let digest = ecc.sha_end(&data[250..]).expect("sha end");
+    assert_eq!(&Sha256::digest(&data)[..], &digest[..]);
+}
+
+#[test]
+fn gpio_round_trip() {
+    let mut ecc = ecc();
+    for high in [true, false] {
+        assert_eq!(high, ecc.gpio_set(high).expect("gpio set"));
+        assert_eq!(high, ecc.gpio_get().expect("gpio get"));
+    }
+}
+
+/// Returns the encrypted slot, the write/read key slot and the 32 byte key
+/// from ECC_ENC_SLOT, ECC_ENC_KEY_SLOT and ECC_ENC_KEY (hex), if configured.
+fn encrypted_slot() -> Option<(u8, u8, Vec<u8>)> {
+    let slot = env::var("ECC_ENC_SLOT").ok()?.parse().ok()?;
+    let key_slot = env::var("ECC_ENC_KEY_SLOT").ok()?.parse().ok()?;
+    let hex = env::var("ECC_ENC_KEY").ok()?;
+    let key = (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16))
+        .collect::<Result<Vec<u8>, _>>()
+        .ok()?;
+    Some((slot, key_slot, key))
+}
+
+#[test]
+fn encrypted_write_read_round_trip() {
+    let (slot, key_slot, key) = match encrypted_slot() {
+        Some(v) => v,
+        None => return,
+    };
+    let mut ecc = ecc();
+    let mut data = [0u8; 32];
+    data.copy_from_slice(&ecc.random().expect("random")[..32]);
+    ecc.write_encrypted(slot, &data, key_slot, &key)
+        .expect("write encrypted");
+    let read = ecc
+        .read_encrypted(slot, key_slot, &key)
+        .expect("read encrypted");
+    assert_eq!(&data[..], &read[..]);
+}
+
+#[test]
+#[cfg(feature = "p256")]
+fn priv_write_sign_verify() {
+    use p256::ecdsa::{signature::hazmat::PrehashVerifier, SigningKey};
+    use sha2::{Digest, Sha256};
+
+    // ECC_PRIV_SLOT is a P256 slot whose SlotConfig.WriteKey is the
+    // ECC_ENC_KEY_SLOT key and which allows encrypted PrivWrite
+    let (_, key_slot, key) = match encrypted_slot() {
+        Some(v) => v,
+        None => return,
+    };
+    let slot: u8 = match env::var("ECC_PRIV_SLOT").ok().and_then(|v| v.parse().ok()) {
+        Some(slot) => slot,
+        None => return,
+    };
+    let scalar = [0x17u8; 32];
+    let mut ecc = ecc();
+    ecc.priv_write(slot, &scalar, key_slot, &key)
+        .expect("priv write");
+    let digest = Sha256::digest(b"priv write round trip");
+    let signature = ecc.sign_digest(slot, &digest).expect("sign digest");
+    let signature = p256::ecdsa::Signature::from_slice(&signature).expect("signature");
+    let verifying_key = *SigningKey::from_slice(&scalar)
+        .expect("signing key")
+        .verifying_key();
+    assert!(verifying_key.verify_prehash(&digest, &signature).is_ok());
+}
+
+#[test]
+fn write_bytes_read_back() {
+    // ECC_WRITE_SLOT is a slot of at least 96 bytes with clear writes allowed
+    let slot: u8 = match env::var("ECC_WRITE_SLOT").ok().and_then(|v| v.parse().ok()) {
+        Some(slot) => slot,
+        None => return,
+    };
+    let mut ecc = ecc();
+    let mut data = vec![];
+    while data.len() < 96 {
+        data.extend_from_slice(&ecc.random().expect("random"));
+    }
+    data.truncate(96);
+    // start one word into the slot so both word and block writes are used
+    ecc.write_bytes(slot, 4, &data).expect("write bytes");
+    let mut read = vec![];
+    for block in 0..4 {
+        read.extend_from_slice(
+            &ecc.read(true, &Address::data(slot, block, 0).expect("address"))
+                .expect("read"),
+        );
+    }
+    assert_eq!(&data[..], &read[4..100]);
+}
+
+#[test]
+fn write_template_read_back() {
+    // an unlocked config zone is lost once this is written, so only run
+    // against devices flagged as disposable with ECC_CONFIG_WRITABLE
+    if env::var("ECC_CONFIG_WRITABLE").is_err() {
+        return;
+    }
+    let mut ecc = ecc();
+    let template = templates::tls_client_cert();
+    ecc.write_config(&template).expect("write config");
+    let config = ecc.read_config().expect("read config");
+    // the bytes that write_config skips can't match the template
+    let skipped = [
+        "aes_enable",
+        "i2c_enable",
+        "user_extra",
+        "user_extra_add",
+        "lock_value",
+        "lock_config",
+    ];
+    let diff: Vec<_> = template
+        .diff(&config)
+        .into_iter()
+        .filter(|diff| !skipped.contains(&diff.field))
+        .collect();
+    assert!(diff.is_empty(), "{:?}", diff);
+}
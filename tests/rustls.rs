@@ -0,0 +1,174 @@
+#![cfg(all(feature = "rustls", feature = "rcgen", feature = "p256"))]
+//! A mutual TLS handshake where the client's CertificateVerify is signed
+//! through `EccSigningKey`, against a software chip that answers the
+//! commands used for signing.
+
+use bytes::BytesMut;
+use ecc608_linux::{Ecc, EccKeyPair, EccSigningKey, Result, Transport};
+use p256::ecdsa::{signature::hazmat::PrehashSigner, SigningKey};
+use rcgen::{BasicConstraints, CertificateParams, IsCa, KeyPair};
+use rustls::{
+    pki_types::{PrivateKeyDer, PrivatePkcs8KeyDer, ServerName},
+    server::WebPkiClientVerifier,
+    sign::{CertifiedKey, SingleCertAndKey},
+    ClientConfig, ClientConnection, Connection, RootCertStore, ServerConfig, ServerConnection,
+};
+use std::{
+    convert::TryFrom,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+/// Emulates GenKey, Random, Nonce and Sign with a P256 key in memory
+struct SoftChip {
+    key: SigningKey,
+    digest: [u8; 32],
+}
+
+impl Transport for SoftChip {
+    fn send_wake(&mut self, _wake_delay: Duration) -> Result {
+        Ok(())
+    }
+
+    fn send_sleep(&mut self) {}
+
+    fn send_idle(&mut self) -> Result {
+        Ok(())
+    }
+
+    fn send_recv_buf(&mut self, _delay: Duration, buf: &mut BytesMut) -> Result {
+        // the word address, count, opcode and parameters precede the data
+        let data = &buf[6..buf.len() - 2];
+        let response = match buf[2] {
+            0x40 => self.key.verifying_key().to_encoded_point(false).as_bytes()[1..].to_vec(),
+            0x1B => vec![0; 32],
+            0x16 => {
+                self.digest.copy_from_slice(data);
+                vec![0x00]
+            }
+            0x41 => {
+                let signature: p256::ecdsa::Signature =
+                    self.key.sign_prehash(&self.digest).expect("sign");
+                signature.to_bytes().to_vec()
+            }
+            opcode => panic!("unexpected opcode {:#04x}", opcode),
+        };
+        buf.clear();
+        buf.extend_from_slice(&[response.len() as u8 + 3]);
+        buf.extend_from_slice(&response);
+        let crc = crc(buf);
+        buf.extend_from_slice(&crc.to_le_bytes());
+        Ok(())
+    }
+
+    fn command_duration(&self, _command: &ecc608_linux::EccCommand) -> Duration {
+        Duration::ZERO
+    }
+
+    fn put_command_flag(&self) -> u8 {
+        0x03
+    }
+}
+
+/// The CRC16 the chip appends to every frame
+fn crc(data: &[u8]) -> u16 {
+    let mut crc: u16 = 0;
+    for byte in data {
+        for bit in 0..8 {
+            let data_bit = (byte >> bit) & 1;
+            let crc_bit = (crc >> 15) as u8;
+            crc <<= 1;
+            if data_bit != crc_bit {
+                crc ^= 0x8005;
+            }
+        }
+    }
+    crc
+}
+
+/// Moves the pending TLS records from one side to the other
+fn transfer(from: &mut Connection, to: &mut Connection) {
+    let mut records = vec![];
+    while from.wants_write() {
+        from.write_tls(&mut records).expect("write tls");
+    }
+    let mut records = &records[..];
+    while !records.is_empty() {
+        to.read_tls(&mut records).expect("read tls");
+    }
+    to.process_new_packets().expect("process packets");
+}
+
+#[test]
+fn mutual_tls_handshake() {
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+
+    let ca_key = KeyPair::generate().expect("ca key");
+    let mut ca_params = CertificateParams::new(vec![]).expect("ca params");
+    ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+    let ca = ca_params.self_signed(&ca_key).expect("ca");
+    let mut roots = RootCertStore::empty();
+    roots.add(ca.der().clone()).expect("root");
+    let roots = Arc::new(roots);
+
+    let server_key = KeyPair::generate().expect("server key");
+    let server_cert = CertificateParams::new(vec!["localhost".to_string()])
+        .expect("server params")
+        .signed_by(&server_key, &ca, &ca_key)
+        .expect("server cert");
+
+    // the client certificate is issued for the public key of the chip
+    let chip = SoftChip {
+        key: SigningKey::from_slice(&[0x01; 32]).expect("signing key"),
+        digest: [0; 32],
+    };
+    let ecc = Arc::new(Mutex::new(Ecc::new(chip)));
+    let client_public = EccKeyPair::new(ecc.clone(), 0)
+        .expect("key pair")
+        .into_key_pair();
+    let client_cert = CertificateParams::new(vec!["device".to_string()])
+        .expect("client params")
+        .signed_by(&client_public, &ca, &ca_key)
+        .expect("client cert");
+
+    let verifier = WebPkiClientVerifier::builder_with_provider(roots.clone(), provider.clone())
+        .build()
+        .expect("client verifier");
+    let server_config = ServerConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()
+        .expect("protocol versions")
+        .with_client_cert_verifier(verifier)
+        .with_single_cert(
+            vec![server_cert.der().clone()],
+            PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(server_key.serialize_der())),
+        )
+        .expect("server config");
+    let client_key = CertifiedKey::new(
+        vec![client_cert.der().clone()],
+        Arc::new(EccSigningKey::new(ecc, 0)),
+    );
+    let client_config = ClientConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .expect("protocol versions")
+        .with_root_certificates(roots)
+        .with_client_cert_resolver(Arc::new(SingleCertAndKey::from(client_key)));
+
+    let mut client = Connection::Client(
+        ClientConnection::new(
+            Arc::new(client_config),
+            ServerName::try_from("localhost").expect("server name"),
+        )
+        .expect("client"),
+    );
+    let mut server =
+        Connection::Server(ServerConnection::new(Arc::new(server_config)).expect("server"));
+    while client.is_handshaking() || server.is_handshaking() {
+        transfer(&mut client, &mut server);
+        transfer(&mut server, &mut client);
+    }
+    // the server only gets here after checking the chip's signature
+    assert_eq!(
+        Some(&[client_cert.der().clone()][..]),
+        server.peer_certificates()
+    );
+}